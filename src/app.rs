@@ -0,0 +1,432 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use gio::Cancellable;
+use gtk::cairo::Region;
+use gtk::{glib, prelude::*};
+
+use crate::colors::{self, Color, Palette};
+use crate::config::{self, Configuration};
+use crate::cursors::Cursors;
+use crate::drawing::backing::Backing;
+use crate::drawing::drawing_tool::{CurrentDrawingTool, DrawingTool, Point};
+use crate::drawing::fill::Fill;
+use crate::drawing::line_style::LineStyle;
+use crate::drawing::step_badge::{self, StepStyle};
+use crate::keybindings::{Action, Keybindings, MouseBindings};
+use crate::presets::Presets;
+use crate::stamps::Stamps;
+use crate::state::State;
+use crate::{color_popover, stamp_popover, swatch, symbol_popover, toast};
+use crate::{drop_empty, track_damage, NO_STAMPS};
+
+/// What the handlers of the overlay share: the config, the elements drawn so far and the pen
+/// the next one is drawn with.
+pub struct App {
+    pub window: gtk::ApplicationWindow,
+    /// the finished elements, events go to it
+    pub draw: gtk::DrawingArea,
+    /// the element that is currently drawn, on top of `draw`
+    pub live: gtk::DrawingArea,
+    pub menu: gtk::Popover,
+    pub config_path: Option<PathBuf>,
+    /// the config as it is in the file, `conf` are the settings of the active profile
+    pub base_conf: RefCell<Configuration>,
+    pub profile: RefCell<Option<String>>,
+    pub conf: RefCell<Configuration>,
+    pub elements: RefCell<Vec<Box<dyn DrawingTool>>>,
+    /// finished elements rasterized once, only the active one is drawn live
+    pub backing: RefCell<Backing>,
+    pub palette: RefCell<Palette>,
+    pub presets: RefCell<Presets>,
+    pub current_tool: RefCell<CurrentDrawingTool>,
+    pub color: RefCell<Color>,
+    pub line_width: RefCell<f64>,
+    pub line_style: RefCell<LineStyle>,
+    pub fill: RefCell<Fill>,
+    pub step_style: RefCell<StepStyle>,
+    /// badges are numbered within their series, `step.reset` starts the next one
+    pub step_series: RefCell<usize>,
+    pub stamps: RefCell<Stamps>,
+    /// remembered for the next session
+    pub state: RefCell<State>,
+    /// the tools cursors, in logical pixels since texture cursors do not know the output scale
+    pub cursors: RefCell<Cursors>,
+    /// keybindings are parsed once, the handlers only look the pressed key up
+    pub keybindings: RefCell<Keybindings>,
+    pub mousebindings: RefCell<MouseBindings>,
+    /// last pointer position, menus open there
+    pub pointer: RefCell<Point>,
+    /// messages on the overlay, e.g. when the config could not be reloaded
+    pub toast: toast::Toast,
+    /// shows the color after a nudge
+    pub swatch: swatch::Swatch,
+    pub color_dialog: gtk::ColorDialog,
+    pub color_popover: color_popover::ColorPopover,
+    pub stamp_popover: stamp_popover::StampPopover,
+    pub symbol_popover: symbol_popover::SymbolPopover,
+}
+
+impl App {
+    /// the cursor shows the tool, color and width of the next stroke, so it is regenerated
+    /// whenever one of them changes
+    pub fn refresh_cursor(&self) {
+        let cursor = self.cursors.borrow().for_tool(
+            &self.current_tool.borrow(),
+            *self.color.borrow(),
+            *self.line_width.borrow(),
+        );
+        if let Some(cur) = cursor {
+            self.draw.set_cursor(Some(&cur));
+        }
+    }
+
+    /// the cursors with the configured size, also when the cursors directory changed
+    pub fn reload_cursors(&self) {
+        *self.cursors.borrow_mut() = Cursors::load(self.conf.borrow().cursor_size.unwrap_or(30));
+        self.refresh_cursor();
+    }
+
+    // make the settings in `conf` the active ones, after a reload or a profile switch.
+    // `previous` are the settings before `conf` changed. What can be changed at runtime, like
+    // the width, is only reset when its setting changed.
+    fn apply_config(&self, previous: &Configuration) {
+        let conf = self.conf.borrow();
+        *self.keybindings.borrow_mut() = Keybindings::new(&conf);
+        *self.palette.borrow_mut() = Palette::new(conf.palette.as_deref().unwrap_or_default());
+        *self.presets.borrow_mut() = Presets::new(conf.presets.as_deref().unwrap_or_default());
+        *self.mousebindings.borrow_mut() =
+            MouseBindings::new(conf.mousebindings.as_ref().unwrap_or(&Default::default()));
+        if conf.line_thickness != previous.line_thickness {
+            *self.line_width.borrow_mut() = conf.line_thickness.unwrap_or(2.0);
+        }
+        if conf.line_style != previous.line_style {
+            *self.line_style.borrow_mut() = conf.line_style.unwrap_or_default();
+        }
+        if conf.fill() != previous.fill() {
+            *self.fill.borrow_mut() = conf.fill();
+        }
+        if conf.step_style != previous.step_style {
+            *self.step_style.borrow_mut() = conf.step_style.unwrap_or_default();
+            *self.step_series.borrow_mut() += 1;
+        }
+        if conf.cursor_size != previous.cursor_size {
+            self.reload_cursors();
+        } else {
+            self.refresh_cursor();
+        }
+    }
+
+    /// the config is reloaded when it changes, an invalid config keeps the old one active. The
+    /// profile stays active as long as the new config still has it.
+    pub fn reload_config(&self) {
+        let new_conf = match config::read_config(self.config_path.as_deref()) {
+            Ok(new_conf) => new_conf,
+            Err(e) => {
+                eprintln!("config not reloaded, {e}");
+                self.toast.error(&format!("config not reloaded, {e}"));
+                return;
+            }
+        };
+        let kept = self
+            .profile
+            .borrow()
+            .clone()
+            .filter(|name| new_conf.has_profile(name));
+        let previous = self.conf.replace(new_conf.with_profile(kept.as_deref()));
+        *self.base_conf.borrow_mut() = new_conf;
+        *self.profile.borrow_mut() = kept;
+        self.apply_config(&previous);
+
+        // the config is applied, but point out what was skipped
+        let diagnostics = config::get_config_file_loc(self.config_path.as_deref())
+            .map(|path| config::validate::check_file(&path))
+            .unwrap_or_default();
+        for diagnostic in &diagnostics {
+            eprintln!("{diagnostic}");
+        }
+        match diagnostics.first() {
+            Some(first) if diagnostics.len() > 1 => self.toast.error(&format!(
+                "{first}\n(and {} more problems)",
+                diagnostics.len() - 1
+            )),
+            Some(first) => self.toast.error(&first.to_string()),
+            None => self.toast.show("config reloaded"),
+        }
+    }
+
+    // a color from the chooser, it is remembered as a recent color
+    fn pick_color(&self, picked: Color) {
+        *self.color.borrow_mut() = picked;
+        self.state.borrow_mut().add_recent_color(picked);
+        self.refresh_cursor();
+    }
+
+    // the full color dialog is a separate window, the overlay has to go below it meanwhile
+    fn open_color_dialog(self: &Rc<Self>) {
+        gtk4_layer_shell::set_layer(&self.window, gtk4_layer_shell::Layer::Bottom);
+        self.color_dialog.choose_rgba(
+            None::<&gtk::Window>,
+            Some(&*self.color.borrow()),
+            None::<&Cancellable>,
+            glib::clone!(@weak self as app => move |c| {
+                gtk4_layer_shell::set_layer(&app.window, gtk4_layer_shell::Layer::Overlay);
+                // an error means the dialog was dismissed
+                if let Ok(c) = c {
+                    app.pick_color(c);
+                }
+            }),
+        );
+    }
+
+    fn select_stamp_tool(&self) {
+        *self.current_tool.borrow_mut() = CurrentDrawingTool::Stamp;
+        self.refresh_cursor();
+    }
+
+    /// run an action of the keybindings, the mouse bindings or the menu
+    pub fn run(self: &Rc<Self>, action: Action) {
+        match action {
+            // TOOLS
+            Action::ToolPencil
+            | Action::ToolArrow
+            | Action::ToolReverseArrow
+            | Action::ToolCurvedArrow
+            | Action::ToolRectangle
+            | Action::ToolPolyline
+            | Action::ToolCallout
+            | Action::ToolStep
+            | Action::ToolStamp => {
+                if let Some(tool) = action.tool() {
+                    *self.current_tool.borrow_mut() = tool;
+                }
+                self.refresh_cursor();
+            }
+            // drawing is started by the mouse handler itself
+            Action::Draw => (),
+            Action::ElementFinish | Action::ElementRemovePoint => {
+                let mut elements = self.elements.borrow_mut();
+                let Some(elem) = elements.last_mut().filter(|elem| elem.active()) else {
+                    return;
+                };
+                if action == Action::ElementFinish {
+                    elem.finish();
+                } else {
+                    elem.remove_last_point();
+                }
+                if elem.active() {
+                    track_damage(&self.live, elem.bounds());
+                } else {
+                    drop_empty(&mut elements);
+                    track_damage(&self.live, None);
+                    self.draw.queue_draw();
+                }
+            }
+            Action::OverlayDisable => {
+                let w = &self.window;
+                gtk4_layer_shell::set_keyboard_mode(w, gtk4_layer_shell::KeyboardMode::None);
+                w.surface().set_input_region(&Region::create());
+                w.unmap();
+                w.map();
+            }
+            Action::OverlayMenu => {
+                let position = *self.pointer.borrow();
+                self.menu.set_pointing_to(Some(&gtk::gdk::Rectangle::new(
+                    position.0 as i32,
+                    position.1 as i32,
+                    1,
+                    1,
+                )));
+                self.menu.popup();
+            }
+            // a normal close, the application exits with status 0
+            Action::OverlayQuit => {
+                if let Some(application) = self.window.application() {
+                    application.quit();
+                }
+            }
+            // line width
+            Action::WidthIncrease | Action::WidthDecrease => {
+                let step = if action == Action::WidthIncrease {
+                    1.0
+                } else {
+                    -1.0
+                };
+                let new_width = (*self.line_width.borrow() + step).max(1.0);
+                *self.line_width.borrow_mut() = new_width;
+                // the element that is still being edited follows, e.g. a stamp is resized
+                let mut elements = self.elements.borrow_mut();
+                if let Some(elem) = elements.last_mut().filter(|elem| elem.active()) {
+                    elem.set_line_width(new_width);
+                    track_damage(&self.live, elem.bounds());
+                }
+                self.refresh_cursor();
+            }
+            // colors
+            Action::PaletteColor(index) => {
+                let selected = self.palette.borrow().get(index);
+                if let Some(selected) = selected {
+                    *self.color.borrow_mut() = selected;
+                    self.refresh_cursor();
+                }
+            }
+            Action::Preset(index) => {
+                let Some(pen) = self.presets.borrow().get(index) else {
+                    return;
+                };
+                if let Some(tool) = pen.tool {
+                    *self.current_tool.borrow_mut() = tool;
+                }
+                if let Some(pen_color) = pen.color {
+                    *self.color.borrow_mut() = pen_color;
+                }
+                if let Some(width) = pen.width {
+                    *self.line_width.borrow_mut() = width;
+                }
+                if let Some(style) = pen.line_style {
+                    *self.line_style.borrow_mut() = style;
+                }
+                self.state.borrow_mut().preset = self.presets.borrow().key(index).map(String::from);
+                self.refresh_cursor();
+            }
+            Action::ColorNext | Action::ColorPrevious => {
+                let step = if action == Action::ColorNext { 1 } else { -1 };
+                let next = self.palette.borrow().cycle(*self.color.borrow(), step);
+                *self.color.borrow_mut() = next;
+                self.refresh_cursor();
+            }
+            // nudges of the current color, the swatch shows the result
+            Action::OpacityIncrease
+            | Action::OpacityDecrease
+            | Action::LightnessIncrease
+            | Action::LightnessDecrease
+            | Action::HueIncrease
+            | Action::HueDecrease => {
+                let current = *self.color.borrow();
+                let next = match action {
+                    Action::OpacityIncrease => colors::with_opacity(current, 0.1),
+                    Action::OpacityDecrease => colors::with_opacity(current, -0.1),
+                    Action::LightnessIncrease => colors::with_lightness(current, 0.05),
+                    Action::LightnessDecrease => colors::with_lightness(current, -0.05),
+                    Action::HueIncrease => colors::with_hue(current, 15.0),
+                    _ => colors::with_hue(current, -15.0),
+                };
+                *self.color.borrow_mut() = next;
+                self.refresh_cursor();
+                self.swatch.show(next);
+            }
+            Action::FillCycle => {
+                let mode = self.fill.borrow().mode.next();
+                self.fill.borrow_mut().mode = mode;
+                self.toast.show(&format!("fill {}", mode.name()));
+            }
+            Action::StepReset => {
+                *self.step_series.borrow_mut() += 1;
+                let first = self.step_style.borrow().label(1);
+                self.toast.show(&format!("steps start over at {first}"));
+            }
+            Action::StepCycle => {
+                let style = self.step_style.borrow().next();
+                *self.step_style.borrow_mut() = style;
+                // a series keeps one style, the badges after the change start over
+                *self.step_series.borrow_mut() += 1;
+                self.toast.show(&format!(
+                    "steps count in {} from {}",
+                    style.name(),
+                    style.label(1)
+                ));
+            }
+            Action::StepRemove => {
+                let point = *self.pointer.borrow();
+                let mut elements = self.elements.borrow_mut();
+                let hit = elements.iter().rposition(|elem| {
+                    !elem.active()
+                        && elem.step_series().is_some()
+                        && elem.bounds().is_some_and(|bounds| bounds.contains(point))
+                });
+                if let Some(index) = hit {
+                    elements.remove(index);
+                    step_badge::renumber(&mut elements);
+                    self.backing.borrow_mut().invalidate();
+                    self.draw.queue_draw();
+                }
+            }
+            Action::StampNext | Action::StampPrevious => {
+                let step = if action == Action::StampNext { 1 } else { -1 };
+                let name = self
+                    .stamps
+                    .borrow_mut()
+                    .cycle(step)
+                    .map(|image| image.name.clone());
+                match name {
+                    Some(name) => {
+                        self.select_stamp_tool();
+                        self.toast.show(&format!("stamp {name}"));
+                    }
+                    None => self.toast.error(NO_STAMPS),
+                }
+            }
+            Action::StampPicker => {
+                if self.stamps.borrow().images().is_empty() {
+                    self.toast.error(NO_STAMPS);
+                    return;
+                }
+                let on_pick: Rc<dyn Fn(usize)> =
+                    Rc::new(glib::clone!(@weak self as app => move |index| {
+                        app.stamps.borrow_mut().select(index);
+                        app.select_stamp_tool();
+                    }));
+                self.stamp_popover.popup(
+                    *self.pointer.borrow(),
+                    self.stamps.borrow().images(),
+                    on_pick,
+                );
+            }
+            Action::StampSymbols => {
+                let on_pick: Rc<dyn Fn(String)> =
+                    Rc::new(glib::clone!(@weak self as app => move |symbol| {
+                        app.stamps.borrow_mut().select_symbol(&symbol);
+                        app.select_stamp_tool();
+                        app.toast.show(&format!("stamp {symbol}"));
+                    }));
+                let favorites = self.conf.borrow().symbol_favorites();
+                self.symbol_popover
+                    .popup(*self.pointer.borrow(), &favorites, on_pick);
+            }
+            Action::LineStyleCycle => {
+                let style = self.line_style.borrow().next();
+                *self.line_style.borrow_mut() = style;
+                self.toast.show(&format!("line style {}", style.name()));
+            }
+            Action::ProfileNext | Action::ProfilePrevious => {
+                let step = if action == Action::ProfileNext { 1 } else { -1 };
+                let next = self
+                    .base_conf
+                    .borrow()
+                    .cycle_profile(self.profile.borrow().as_deref(), step);
+                let settings = self.base_conf.borrow().with_profile(next.as_deref());
+                let previous = self.conf.replace(settings);
+                self.toast
+                    .show(&format!("profile {}", next.as_deref().unwrap_or("default")));
+                *self.profile.borrow_mut() = next;
+                self.apply_config(&previous);
+            }
+            Action::ColorChooser => {
+                let recent = self.state.borrow().recent_colors();
+                let on_pick: Rc<dyn Fn(Color)> =
+                    Rc::new(glib::clone!(@weak self as app => move |picked| {
+                        app.pick_color(picked);
+                    }));
+                let on_more: Rc<dyn Fn()> = Rc::new(glib::clone!(@weak self as app => move || {
+                    app.open_color_dialog();
+                }));
+                self.color_popover.popup(
+                    *self.pointer.borrow(),
+                    self.palette.borrow().colors(),
+                    &recent,
+                    on_pick,
+                    on_more,
+                );
+            }
+        };
+    }
+}
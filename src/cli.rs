@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+
+/// Environment variable with the path of the config file, `--config` takes precedence.
+const CONFIG_ENV: &str = "CHICOLLI_CONFIG";
+
+pub const USAGE: &str =
+    "usage: chicolli [--config <path>] [--profile <name>] [--check-config [path]]";
+
+/// The command line options.
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// a config file to use instead of the one in the config directory
+    pub config: Option<PathBuf>,
+    /// the profile of the config to start with
+    pub profile: Option<String>,
+    /// only check the config, the path is optional
+    pub check_config: Option<Option<PathBuf>>,
+}
+
+impl Options {
+    /// parse the arguments without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            config: std::env::var_os(CONFIG_ENV)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            ..Options::default()
+        };
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            match name {
+                "--config" => {
+                    let path = value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| String::from("--config needs a path"))?;
+                    options.config = Some(PathBuf::from(path));
+                }
+                "--profile" => {
+                    let name = value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| String::from("--profile needs a name"))?;
+                    options.profile = Some(name);
+                }
+                "--check-config" => {
+                    // the path is optional, the next option is not a path
+                    let path = value.or_else(|| args.next_if(|next| !next.starts_with("--")));
+                    options.check_config = Some(path.map(PathBuf::from));
+                }
+                _ => return Err(format!("unknown argument \"{arg}\"")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn check_config_without_path() {
+        assert_eq!(parse(&["--check-config"]).unwrap().check_config, Some(None));
+        let options = parse(&["--check-config", "--profile", "work"]).unwrap();
+        assert_eq!(options.check_config, Some(None));
+        assert_eq!(options.profile.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn check_config_with_path() {
+        let path = Some(Some(PathBuf::from("/tmp/chicolli.toml")));
+        assert_eq!(
+            parse(&["--check-config", "/tmp/chicolli.toml"])
+                .unwrap()
+                .check_config,
+            path
+        );
+        assert_eq!(
+            parse(&["--check-config=/tmp/chicolli.toml"])
+                .unwrap()
+                .check_config,
+            path
+        );
+    }
+
+    #[test]
+    fn options_with_values() {
+        let options = parse(&["--config", "a.json", "--profile=work"]).unwrap();
+        assert_eq!(options.config, Some(PathBuf::from("a.json")));
+        assert_eq!(options.profile.as_deref(), Some("work"));
+        assert_eq!(options.check_config, None);
+        assert!(parse(&["--config"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+    }
+}
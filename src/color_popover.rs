@@ -0,0 +1,98 @@
+use std::rc::Rc;
+
+use gtk::{glib, prelude::*};
+
+use crate::colors::Color;
+use crate::drawing::drawing_tool::Point;
+
+const SWATCH_SIZE: i32 = 24;
+
+/// The color chooser on the layer surface itself, so choosing a color does not have to drop
+/// the overlay below other windows: the palette, the recently chosen colors and a button for
+/// the full color dialog.
+pub struct ColorPopover {
+    popover: gtk::Popover,
+    content: gtk::Box,
+}
+
+impl ColorPopover {
+    pub fn new(parent: &impl IsA<gtk::Widget>) -> ColorPopover {
+        let popover = gtk::Popover::new();
+        popover.set_parent(parent);
+        popover.set_has_arrow(false);
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        popover.set_child(Some(&content));
+        parent.connect_destroy(glib::clone!(@weak popover => move |_| popover.unparent()));
+        ColorPopover { popover, content }
+    }
+
+    /// show the popover at `position`, the colors are a name for the tooltip and the color.
+    /// `on_pick` gets the chosen swatch, `on_more` is called for the color dialog button.
+    pub fn popup(
+        &self,
+        position: Point,
+        palette: &[(String, Color)],
+        recent: &[(String, Color)],
+        on_pick: Rc<dyn Fn(Color)>,
+        on_more: Rc<dyn Fn()>,
+    ) {
+        // rebuilt every time, the palette and the recent colors change
+        while let Some(child) = self.content.first_child() {
+            self.content.remove(&child);
+        }
+        for colors in [palette, recent] {
+            if colors.is_empty() {
+                continue;
+            }
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+            for (name, color) in colors {
+                row.append(&self.swatch(name, *color, on_pick.clone()));
+            }
+            self.content.append(&row);
+        }
+        let more = gtk::Button::with_label("More colors…");
+        more.add_css_class("flat");
+        more.connect_clicked(glib::clone!(@weak self.popover as popover => move |_| {
+            popover.popdown();
+            on_more();
+        }));
+        self.content.append(&more);
+
+        self.popover.set_pointing_to(Some(&gtk::gdk::Rectangle::new(
+            position.0 as i32,
+            position.1 as i32,
+            1,
+            1,
+        )));
+        self.popover.popup();
+    }
+
+    fn swatch(&self, name: &str, color: Color, on_pick: Rc<dyn Fn(Color)>) -> gtk::Button {
+        let area = gtk::DrawingArea::new();
+        area.set_content_width(SWATCH_SIZE);
+        area.set_content_height(SWATCH_SIZE);
+        area.set_draw_func(move |_, ctx, width, height| {
+            ctx.rectangle(0.0, 0.0, width as f64, height as f64);
+            ctx.set_source_rgba(
+                color.red().into(),
+                color.green().into(),
+                color.blue().into(),
+                color.alpha().into(),
+            );
+            if let Err(e) = ctx.fill() {
+                eprintln!("could not draw swatch: {e}");
+            }
+        });
+
+        let button = gtk::Button::new();
+        button.add_css_class("flat");
+        button.add_css_class("swatch");
+        button.set_tooltip_text(Some(name));
+        button.set_child(Some(&area));
+        button.connect_clicked(glib::clone!(@weak self.popover as popover => move |_| {
+            popover.popdown();
+            on_pick(color);
+        }));
+        button
+    }
+}
@@ -1,7 +1,209 @@
+use crate::config::PaletteColor;
+
 pub type Color = gtk::gdk::RGBA;
 
 pub const RED: Color = Color::RED;
 
-pub const BLUE: Color = Color::BLUE;
+const LIGHTNESS_RANGE: (f32, f32) = (0.02, 0.98);
+
+/// The colors of the config palette, in the order `color.next` goes through them.
+pub struct Palette {
+    colors: Vec<(String, Color)>,
+}
+
+impl Palette {
+    /// entries with a color that can not be parsed are skipped, `config::validate` reports them
+    pub fn new(config: &[PaletteColor]) -> Palette {
+        Palette {
+            colors: config
+                .iter()
+                .filter_map(|entry| Some((entry.name.clone(), parse(&entry.color).ok()?)))
+                .collect(),
+        }
+    }
+
+    /// the names and colors, in order
+    pub fn colors(&self) -> &[(String, Color)] {
+        &self.colors
+    }
+
+    pub fn get(&self, index: usize) -> Option<Color> {
+        self.colors.get(index).map(|(_, color)| *color)
+    }
+
+    /// the color to start with
+    pub fn first(&self) -> Color {
+        self.get(0).unwrap_or(RED)
+    }
+
+    /// step through the palette from `current`, keeping its opacity
+    pub fn cycle(&self, current: Color, step: isize) -> Color {
+        if self.colors.is_empty() {
+            return current;
+        }
+        let same_rgb = |(_, c): &(String, Color)| {
+            c.red() == current.red() && c.green() == current.green() && c.blue() == current.blue()
+        };
+        let len = self.colors.len() as isize;
+        let next = match self.colors.iter().position(same_rgb) {
+            Some(index) => (index as isize + step).rem_euclid(len),
+            // a color from the chooser, start at the beginning
+            None => 0,
+        };
+        let mut color = self.colors[next as usize].1;
+        color.set_alpha(current.alpha());
+        color
+    }
+}
+
+/// parse a CSS color like `#ff8800`, `rgba(255, 136, 0, 0.5)` or `orange`
+pub fn parse(color: &str) -> Result<Color, String> {
+    Color::parse(color).map_err(|_| format!("invalid color \"{color}\""))
+}
+
+/// change the opacity by `step`, fully transparent strokes would be invisible so it stops at
+/// a tenth
+pub fn with_opacity(current: Color, step: f32) -> Color {
+    let mut color = current;
+    color.set_alpha((current.alpha() + step).clamp(0.1, 1.0));
+    color
+}
+
+/// change the lightness by `step` in HSL, keeping hue, saturation and opacity
+pub fn with_lightness(current: Color, step: f32) -> Color {
+    let (hue, saturation, lightness) = to_hsl(current);
+    // pure black or white has no hue left to go back to, so steps stop just before them
+    let (min, max) = (
+        LIGHTNESS_RANGE.0.min(lightness),
+        LIGHTNESS_RANGE.1.max(lightness),
+    );
+    from_hsl(
+        hue,
+        saturation,
+        (lightness + step).clamp(min, max),
+        current.alpha(),
+    )
+}
+
+/// rotate the hue by `degrees`, keeping saturation, lightness and opacity
+pub fn with_hue(current: Color, degrees: f32) -> Color {
+    let (hue, saturation, lightness) = to_hsl(current);
+    from_hsl(
+        (hue + degrees).rem_euclid(360.0),
+        saturation,
+        lightness,
+        current.alpha(),
+    )
+}
+
+// hue in degrees, saturation and lightness from 0 to 1
+fn to_hsl(color: Color) -> (f32, f32, f32) {
+    let (r, g, b) = (color.red(), color.green(), color.blue());
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta <= f32::EPSILON {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    (hue * 60.0, saturation.min(1.0), lightness)
+}
+
+fn from_hsl(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Color {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    Color::new(r + m, g + m, b + m, alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        let components = |c: Color| [c.red(), c.green(), c.blue(), c.alpha()];
+        for (a, b) in components(a).into_iter().zip(components(b)) {
+            assert!((a - b).abs() < 1e-4, "{a} != {b}");
+        }
+    }
+
+    fn palette() -> Palette {
+        let entry = |name: &str, color: &str| PaletteColor {
+            name: String::from(name),
+            color: String::from(color),
+            key: None,
+        };
+        Palette::new(&[
+            entry("red", "#ff0000"),
+            entry("green", "#00ff00"),
+            entry("blue", "#0000ff"),
+        ])
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        let (hue, saturation, lightness) = to_hsl(RED);
+        assert_eq!((hue, saturation, lightness), (0.0, 1.0, 0.5));
+        for color in [
+            Color::new(1.0, 0.0, 0.0, 1.0),
+            Color::new(0.2, 0.6, 0.4, 0.5),
+            Color::new(0.9, 0.8, 0.1, 1.0),
+            Color::new(0.1, 0.2, 0.9, 1.0),
+            Color::new(0.5, 0.5, 0.5, 1.0),
+        ] {
+            let (hue, saturation, lightness) = to_hsl(color);
+            assert_close(from_hsl(hue, saturation, lightness, color.alpha()), color);
+        }
+    }
+
+    #[test]
+    fn hue_rotates() {
+        assert_close(with_hue(RED, 120.0), Color::new(0.0, 1.0, 0.0, 1.0));
+        assert_close(with_hue(RED, -120.0), Color::new(0.0, 0.0, 1.0, 1.0));
+        assert_close(with_hue(RED, 360.0), RED);
+    }
+
+    #[test]
+    fn lightness_keeps_the_hue() {
+        let light = with_lightness(RED, 1.0);
+        assert!((to_hsl(light).2 - 0.98).abs() < 1e-4);
+        assert_close(with_lightness(light, -0.48), RED);
+        let dark = with_lightness(RED, -1.0);
+        assert!((to_hsl(dark).2 - 0.02).abs() < 1e-4);
+        assert_close(with_lightness(dark, 0.48), RED);
+        // a color already past the range is not pulled back into it
+        assert_close(with_lightness(Color::WHITE, 0.1), Color::WHITE);
+    }
 
-pub const GREEN: Color = Color::GREEN;
+    #[test]
+    fn palette_cycles() {
+        let palette = palette();
+        let green = palette.get(1).unwrap();
+        let blue = palette.get(2).unwrap();
+        assert_close(palette.cycle(RED, 1), green);
+        assert_close(palette.cycle(RED, -1), blue);
+        assert_close(palette.cycle(blue, 1), RED);
+        // the opacity stays, a color that is not in the palette starts over
+        let mut translucent = RED;
+        translucent.set_alpha(0.5);
+        assert_eq!(palette.cycle(translucent, 1).alpha(), 0.5);
+        assert_close(palette.cycle(Color::WHITE, 1), RED);
+    }
+}
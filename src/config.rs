@@ -1,97 +1,586 @@
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
+    fmt,
     fs::File,
-    io::{Error, ErrorKind, Read},
+    io::Read,
+    path::{Path, PathBuf},
 };
 
 use dirs::config_dir;
+use gtk::pango::FontDescription;
 
-#[derive(Debug, Deserialize, Serialize)]
+use crate::colors;
+use crate::drawing::arrow_head::{ArrowHeads, HeadStyle};
+use crate::drawing::drawing_tool::CurrentDrawingTool;
+use crate::drawing::fill::{Fill, FillMode};
+use crate::drawing::line_style::LineStyle;
+use crate::drawing::step_badge::StepStyle;
+use crate::error::{Error, Result};
+use crate::keybindings::{Accelerator, MouseAccelerator};
+
+pub mod validate;
+
+/// One binding like `"<Ctrl><Shift>z"` or `"<Shift>scroll-up"`, or a list of them.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let keys = match self {
+            KeyList::One(key) => std::slice::from_ref(key),
+            KeyList::Many(keys) => keys.as_slice(),
+        };
+        keys.iter().map(String::as_str)
+    }
+}
+
+/// A named color of the palette, `color` is any CSS color like `#ff8800`,
+/// `rgba(255, 136, 0, 0.5)` or `orange`. The optional key selects it.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PaletteColor {
+    pub name: String,
+    pub color: String,
+    pub key: Option<String>,
+}
+
+/// A pen preset, selected by its key. The key wins over a default binding of the same key, so
+/// e.g. `1` can select a preset instead of the pencil. `tool` is the name of a tool as in the
+/// `tool.*` actions, e.g. `arrow`, and `color` a CSS color like the palette's. What a preset
+/// leaves out stays as it is.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Preset {
+    pub tool: Option<String>,
+    pub color: Option<String>,
+    pub width: Option<f64>,
+    pub line_style: Option<LineStyle>,
+    pub key: Option<String>,
+}
+
+/// How the arrow tools end, see `ArrowHeads`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ArrowConfig {
+    /// the length of a head in line widths
+    pub head_length: Option<f64>,
+    /// the angle between the shaft and each side of a head, in degrees
+    pub head_angle: Option<f64>,
+    /// `open`, `triangle`, `circle`, `diamond`, `bar` or `none`
+    pub start: Option<HeadStyle>,
+    pub end: Option<HeadStyle>,
+}
+
+/// How closed shapes are filled, `fill.cycle` changes the mode at runtime.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FillConfig {
+    /// `none`, `solid` or `translucent`
+    pub mode: Option<FillMode>,
+    /// a CSS color, without one shapes are filled with their stroke color
+    pub color: Option<String>,
+    /// the opacity of a translucent fill, from 0 to 1
+    pub opacity: Option<f64>,
+}
+
+/// How rectangles are drawn.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RectangleConfig {
+    /// the radius of the corners in pixels, 0 keeps them sharp
+    pub corner_radius: Option<f64>,
+}
+
+/// How the speech bubbles of the callout tool are drawn.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CalloutConfig {
+    pub corner_radius: Option<f64>,
+    /// a Pango font description like `Sans Bold 14`
+    pub font: Option<String>,
+}
+
+/// The symbol picker of the stamp tool.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SymbolsConfig {
+    /// the symbols shown above the search results, e.g. `["✔", "✘", "⚠"]`
+    pub favorites: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Configuration {
     pub line_thickness: Option<f64>,
-    pub draw_keybind: Option<String>,
-    pub arrow_keybind: Option<String>,
-    pub reverse_arrow_keybind: Option<String>,
-    pub rectangle_keybind: Option<String>,
-    pub disable_drawing: Option<String>,
-    pub color_r: Option<String>,
-    pub color_g: Option<String>,
-    pub color_b: Option<String>,
-    pub color_chooser: Option<String>,
+    pub cursor_size: Option<i32>,
+    /// action name (e.g. `tool.pencil`) to the keys that trigger it, on top of the defaults. An
+    /// empty list unbinds an action.
+    pub keybindings: Option<BTreeMap<String, KeyList>>,
+    /// action name to mouse buttons (`button2`) and scroll directions (`scroll-up`)
+    pub mousebindings: Option<BTreeMap<String, KeyList>>,
+    /// the colors `color.next` and `color.previous` go through
+    pub palette: Option<Vec<PaletteColor>>,
+    pub presets: Option<Vec<Preset>>,
+    pub arrow: Option<ArrowConfig>,
+    pub fill: Option<FillConfig>,
+    /// `solid`, `dashed`, `dotted`, `dash_dot` or `double`, `line_style.cycle` changes it
+    pub line_style: Option<LineStyle>,
+    pub rectangle: Option<RectangleConfig>,
+    pub callout: Option<CalloutConfig>,
+    /// `numbers`, `letters` or `roman`, how step badges count. `step.cycle` changes it.
+    pub step_style: Option<StepStyle>,
+    pub symbols: Option<SymbolsConfig>,
+    /// named settings layered over the rest of the config, e.g. `[profiles.teaching]`
+    pub profiles: Option<BTreeMap<String, Configuration>>,
 }
 
-impl Configuration {
-    fn minimal() -> Self {
-        Configuration {
-            line_thickness: Some(2.0),
-            draw_keybind: None,
-            arrow_keybind: None,
-            reverse_arrow_keybind: None,
-            rectangle_keybind: None,
-            disable_drawing: None,
-            color_r: None,
-            color_g: None,
-            color_b: None,
-            color_chooser: None,
+fn default_keybindings() -> BTreeMap<String, KeyList> {
+    [
+        ("tool.pencil", "1"),
+        ("tool.arrow", "2"),
+        ("tool.reverse_arrow", "3"),
+        ("tool.rectangle", "4"),
+        ("tool.curved_arrow", "5"),
+        ("tool.polyline", "6"),
+        ("tool.callout", "7"),
+        ("tool.step", "8"),
+        ("step.reset", "n"),
+        ("step.cycle", "<Shift>n"),
+        ("step.remove", "Delete"),
+        ("tool.stamp", "9"),
+        ("stamp.next", "bracketright"),
+        ("stamp.previous", "bracketleft"),
+        ("stamp.picker", "i"),
+        ("stamp.symbols", "e"),
+        ("element.finish", "Return"),
+        ("element.remove_point", "BackSpace"),
+        ("fill.cycle", "f"),
+        ("color.next", "period"),
+        ("color.previous", "comma"),
+        ("line_style.cycle", "s"),
+        ("overlay.disable", "d"),
+        ("color.chooser", "c"),
+        ("opacity.increase", "<Alt>Up"),
+        ("opacity.decrease", "<Alt>Down"),
+        ("lightness.increase", "<Shift>Up"),
+        ("lightness.decrease", "<Shift>Down"),
+        ("hue.increase", "<Shift>Right"),
+        ("hue.decrease", "<Shift>Left"),
+        ("profile.next", "p"),
+        ("profile.previous", "<Shift>p"),
+    ]
+    .into_iter()
+    .map(|(action, key)| (String::from(action), KeyList::One(String::from(key))))
+    .collect()
+}
+
+/// Where a single key field of the configs from before the keybindings section went.
+#[derive(Debug, Clone, Copy)]
+pub enum Legacy {
+    /// the key of this action in `keybindings`
+    Action(&'static str),
+    /// the key of the palette color with this name
+    PaletteColor(&'static str),
+}
+
+impl fmt::Display for Legacy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Legacy::Action(action) => write!(f, "\"{action}\" in keybindings"),
+            Legacy::PaletteColor(name) => write!(f, "the key of the palette color \"{name}\""),
         }
     }
 }
 
+/// the single key fields of old configs, they are still read
+pub const LEGACY_FIELDS: [(&str, Legacy); 9] = [
+    ("draw_keybind", Legacy::Action("tool.pencil")),
+    ("arrow_keybind", Legacy::Action("tool.arrow")),
+    (
+        "reverse_arrow_keybind",
+        Legacy::Action("tool.reverse_arrow"),
+    ),
+    ("rectangle_keybind", Legacy::Action("tool.rectangle")),
+    ("disable_drawing", Legacy::Action("overlay.disable")),
+    ("color_chooser", Legacy::Action("color.chooser")),
+    ("color_r", Legacy::PaletteColor("red")),
+    ("color_g", Legacy::PaletteColor("green")),
+    ("color_b", Legacy::PaletteColor("blue")),
+];
+
+fn default_palette() -> Vec<PaletteColor> {
+    [
+        ("red", "#ff0000", "r"),
+        ("green", "#00ff00", "g"),
+        ("blue", "#0000ff", "b"),
+    ]
+    .into_iter()
+    .map(|(name, color, key)| PaletteColor {
+        name: String::from(name),
+        color: String::from(color),
+        key: Some(String::from(key)),
+    })
+    .collect()
+}
+
+fn default_arrow() -> ArrowConfig {
+    let heads = ArrowHeads::default();
+    ArrowConfig {
+        head_length: Some(heads.length),
+        head_angle: Some(heads.angle.to_degrees()),
+        start: Some(heads.start),
+        end: Some(heads.end),
+    }
+}
+
+fn default_fill() -> FillConfig {
+    let fill = Fill::default();
+    FillConfig {
+        mode: Some(fill.mode),
+        color: None,
+        opacity: Some(fill.opacity),
+    }
+}
+
+fn default_rectangle() -> RectangleConfig {
+    RectangleConfig {
+        corner_radius: Some(0.0),
+    }
+}
+
+fn default_callout() -> CalloutConfig {
+    CalloutConfig {
+        corner_radius: Some(DEFAULT_CALLOUT_RADIUS),
+        font: Some(String::from(DEFAULT_CALLOUT_FONT)),
+    }
+}
+
+fn default_symbols() -> SymbolsConfig {
+    SymbolsConfig {
+        favorites: Some(
+            DEFAULT_FAVORITES
+                .iter()
+                .copied()
+                .map(String::from)
+                .collect(),
+        ),
+    }
+}
+
+fn default_mousebindings() -> BTreeMap<String, KeyList> {
+    [
+        ("draw", vec!["button1"]),
+        ("tool.arrow", vec!["button2"]),
+        ("overlay.menu", vec!["button3"]),
+        ("width.increase", vec!["scroll-up"]),
+        ("width.decrease", vec!["scroll-down"]),
+        ("color.next", vec!["<Ctrl>scroll-down"]),
+        ("color.previous", vec!["<Ctrl>scroll-up"]),
+        (
+            "opacity.increase",
+            vec!["<Shift>scroll-up", "<Shift>scroll-right"],
+        ),
+        (
+            "opacity.decrease",
+            vec!["<Shift>scroll-down", "<Shift>scroll-left"],
+        ),
+    ]
+    .into_iter()
+    .map(|(action, buttons)| {
+        let buttons = buttons.into_iter().map(String::from).collect();
+        (String::from(action), KeyList::Many(buttons))
+    })
+    .collect()
+}
+
 impl Default for Configuration {
     fn default() -> Self {
         Configuration {
             line_thickness: Some(2.0),
-            draw_keybind: Some(String::from("1")),
-            arrow_keybind: Some(String::from("2")),
-            reverse_arrow_keybind: Some(String::from("3")),
-            rectangle_keybind: Some(String::from("4")),
-            disable_drawing: Some(String::from("d")),
-            color_r: Some(String::from("r")),
-            color_g: Some(String::from("g")),
-            color_b: Some(String::from("b")),
-            color_chooser: Some(String::from("c")),
+            cursor_size: Some(30),
+            // a config without a keybindings section keeps the default keys
+            keybindings: Some(default_keybindings()),
+            mousebindings: Some(default_mousebindings()),
+            palette: Some(default_palette()),
+            presets: Some(Vec::new()),
+            arrow: Some(default_arrow()),
+            fill: Some(default_fill()),
+            line_style: Some(LineStyle::Solid),
+            rectangle: Some(default_rectangle()),
+            callout: Some(default_callout()),
+            step_style: Some(StepStyle::Numbers),
+            symbols: Some(default_symbols()),
+            profiles: None,
         }
     }
 }
 
 impl Configuration {
+    /// the settings of `self` with what it leaves out taken from `other_config`. The bindings
+    /// are combined per action, so only the actions bound differently have to be listed, and the
+    /// keys of the palette colors and presets of `self` win over the bindings of `other_config`.
     pub fn merge(self, other_config: Self) -> Self {
+        let entry_keys: Vec<&str> = self
+            .palette
+            .iter()
+            .flatten()
+            .filter_map(|entry| entry.key.as_deref())
+            .chain(
+                self.presets
+                    .iter()
+                    .flatten()
+                    .filter_map(|preset| preset.key.as_deref()),
+            )
+            .collect();
+        let keybindings = merge_bindings(
+            self.keybindings,
+            other_config.keybindings,
+            &entry_keys,
+            Accelerator::parse,
+        );
         Configuration {
             line_thickness: self.line_thickness.or(other_config.line_thickness),
-            draw_keybind: self.draw_keybind.or(other_config.draw_keybind),
-            arrow_keybind: self.arrow_keybind.or(other_config.arrow_keybind),
-            reverse_arrow_keybind: self
-                .reverse_arrow_keybind
-                .or(other_config.reverse_arrow_keybind),
-            rectangle_keybind: self.rectangle_keybind.or(other_config.rectangle_keybind),
-            disable_drawing: self.disable_drawing.or(other_config.disable_drawing),
-            color_r: self.color_r.or(other_config.color_r),
-            color_g: self.color_g.or(other_config.color_g),
-            color_b: self.color_b.or(other_config.color_b),
-            color_chooser: self.color_chooser.or(other_config.color_chooser),
+            cursor_size: self.cursor_size.or(other_config.cursor_size),
+            keybindings,
+            mousebindings: merge_bindings(
+                self.mousebindings,
+                other_config.mousebindings,
+                &[],
+                MouseAccelerator::parse,
+            ),
+            palette: self.palette.or(other_config.palette),
+            presets: self.presets.or(other_config.presets),
+            arrow: self.arrow.or(other_config.arrow),
+            fill: self.fill.or(other_config.fill),
+            line_style: self.line_style.or(other_config.line_style),
+            rectangle: self.rectangle.or(other_config.rectangle),
+            callout: self.callout.or(other_config.callout),
+            step_style: self.step_style.or(other_config.step_style),
+            symbols: self.symbols.or(other_config.symbols),
+            profiles: self.profiles.or(other_config.profiles),
         }
     }
+
+    /// the settings with the profile `name` layered over them, `None` or an unknown name give
+    /// the settings without any profile
+    pub fn with_profile(&self, name: Option<&str>) -> Configuration {
+        let base = Configuration {
+            profiles: None,
+            ..self.clone()
+        };
+        match name.and_then(|name| self.profiles.as_ref()?.get(name)) {
+            Some(profile) => Configuration {
+                profiles: None,
+                ..profile.clone().merge(base)
+            },
+            None => base,
+        }
+    }
+
+    // take over the single key fields of an old config, `document` is the file as it is
+    // written. What the config also sets the new way wins.
+    fn migrate_legacy(&mut self, document: &serde_json::Value) {
+        let explicit_palette = self.palette.is_some();
+        for (field, legacy) in LEGACY_FIELDS {
+            let Some(key) = document.get(field).and_then(serde_json::Value::as_str) else {
+                continue;
+            };
+            match legacy {
+                Legacy::Action(action) => {
+                    self.keybindings
+                        .get_or_insert_with(BTreeMap::new)
+                        .entry(String::from(action))
+                        .or_insert_with(|| KeyList::One(String::from(key)));
+                }
+                Legacy::PaletteColor(name) => {
+                    let palette = self.palette.get_or_insert_with(default_palette);
+                    if let Some(entry) = palette.iter_mut().find(|entry| entry.name == name) {
+                        if !explicit_palette || entry.key.is_none() {
+                            entry.key = Some(String::from(key));
+                        }
+                    }
+                }
+            }
+        }
+        for (name, profile) in self.profiles.iter_mut().flatten() {
+            if let Some(document) = document
+                .get("profiles")
+                .and_then(|profiles| profiles.get(name))
+            {
+                profile.migrate_legacy(document);
+            }
+        }
+    }
+
+    /// the arrow heads, what the config leaves out is the default
+    pub fn arrow_heads(&self) -> ArrowHeads {
+        let default = ArrowHeads::default();
+        let Some(arrow) = &self.arrow else {
+            return default;
+        };
+        ArrowHeads {
+            start: arrow.start.unwrap_or(default.start),
+            end: arrow.end.unwrap_or(default.end),
+            length: arrow.head_length.unwrap_or(default.length),
+            angle: arrow
+                .head_angle
+                .map(f64::to_radians)
+                .unwrap_or(default.angle),
+        }
+    }
+
+    /// the fill of closed shapes, an invalid color fills with the stroke color
+    pub fn fill(&self) -> Fill {
+        let default = Fill::default();
+        let Some(fill) = &self.fill else {
+            return default;
+        };
+        Fill {
+            mode: fill.mode.unwrap_or(default.mode),
+            color: fill
+                .color
+                .as_deref()
+                .and_then(|color| colors::parse(color).ok()),
+            opacity: fill.opacity.unwrap_or(default.opacity),
+        }
+    }
+
+    /// how much the corners of what `tool` draws are rounded, tools without corners ignore it
+    pub fn corner_radius(&self, tool: CurrentDrawingTool) -> f64 {
+        match tool {
+            CurrentDrawingTool::Callout => self
+                .callout
+                .as_ref()
+                .and_then(|callout| callout.corner_radius)
+                .unwrap_or(DEFAULT_CALLOUT_RADIUS),
+            _ => self
+                .rectangle
+                .as_ref()
+                .and_then(|rectangle| rectangle.corner_radius)
+                .unwrap_or(0.0),
+        }
+    }
+
+    /// the font of the callout text
+    pub fn callout_font(&self) -> FontDescription {
+        let font = self
+            .callout
+            .as_ref()
+            .and_then(|callout| callout.font.as_deref())
+            .unwrap_or(DEFAULT_CALLOUT_FONT);
+        FontDescription::from_string(font)
+    }
+
+    /// the favorites of the symbol picker
+    pub fn symbol_favorites(&self) -> Vec<String> {
+        self.symbols
+            .as_ref()
+            .and_then(|symbols| symbols.favorites.clone())
+            .unwrap_or_else(|| {
+                DEFAULT_FAVORITES
+                    .iter()
+                    .copied()
+                    .map(String::from)
+                    .collect()
+            })
+    }
+
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profiles
+            .as_ref()
+            .is_some_and(|profiles| profiles.contains_key(name))
+    }
+
+    /// the profile `step` places after `current` in alphabetical order, going through the
+    /// settings without a profile (`None`) between the last and the first one
+    pub fn cycle_profile(&self, current: Option<&str>, step: isize) -> Option<String> {
+        let mut names: Vec<Option<&str>> = vec![None];
+        names.extend(
+            self.profiles
+                .iter()
+                .flatten()
+                .map(|(name, _)| Some(name.as_str())),
+        );
+        let position = names.iter().position(|name| *name == current).unwrap_or(0);
+        let next = (position as isize + step).rem_euclid(names.len() as isize) as usize;
+        names[next].map(String::from)
+    }
 }
 
+// `bindings` over `base` per action. The keys `bindings` and `entry_keys` use are taken away
+// from the other actions of `base`, so a key bound in the config wins over a default that uses
+// it.
+fn merge_bindings<T: PartialEq>(
+    bindings: Option<BTreeMap<String, KeyList>>,
+    base: Option<BTreeMap<String, KeyList>>,
+    entry_keys: &[&str],
+    parse: fn(&str) -> std::result::Result<T, String>,
+) -> Option<BTreeMap<String, KeyList>> {
+    let Some(base) = base else {
+        return bindings;
+    };
+    let taken: Vec<T> = bindings
+        .iter()
+        .flat_map(BTreeMap::values)
+        .flat_map(KeyList::iter)
+        .chain(entry_keys.iter().copied())
+        .filter_map(|key| parse(key).ok())
+        .collect();
+    let mut merged: BTreeMap<String, KeyList> = base
+        .into_iter()
+        .filter(|(action, _)| {
+            !bindings
+                .as_ref()
+                .is_some_and(|bindings| bindings.contains_key(action))
+        })
+        .map(|(action, keys)| {
+            let free = keys
+                .iter()
+                .filter(|key| parse(key).map_or(true, |key| !taken.contains(&key)))
+                .map(String::from)
+                .collect();
+            (action, KeyList::Many(free))
+        })
+        .collect();
+    merged.extend(bindings.into_iter().flatten());
+    Some(merged)
+}
+
+const DEFAULT_CALLOUT_RADIUS: f64 = 12.0;
+const DEFAULT_CALLOUT_FONT: &str = "Sans 14";
+const DEFAULT_FAVORITES: [&str; 5] = ["✔", "✘", "⚠", "★", "➜"];
+
 pub const PENCIL_CUR: &str = "pencil";
 pub const ARROW_CUR: &str = "arrow";
 pub const SQUARE_CUR: &str = "rectangle";
 
 const CONFIG_NAME: &str = "chicolli.json";
+// preferred over the json file when both exist
+const CONFIG_TOML_NAME: &str = "chicolli.toml";
 const CONFIG_DIR: &str = "chicolli";
 const CONFIG_CURSORS_DIR: &str = "cursors";
+const CONFIG_STAMPS_DIR: &str = "stamps";
+/// optional file in the cursors directory with the file, size and hotspot of every cursor
+pub const CURSORS_MANIFEST: &str = "cursors.json";
 
-fn write_default_config(path: &std::path::Path) {
-    let mut file = std::fs::File::create(path).unwrap();
-    serde_json::to_writer_pretty(&mut file, &Configuration::default()).unwrap();
+fn write_default_config(path: &std::path::Path) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    serde_json::to_writer_pretty(&mut file, &Configuration::default())?;
+    Ok(())
 }
 
-pub fn get_config() -> Configuration {
-    match read_config() {
-        Ok(conf) => conf,
+/// `explicit` is a config file given on the command line or in the environment, it is used
+/// instead of the config directory
+pub fn get_config(explicit: Option<&Path>) -> Configuration {
+    match read_config(explicit) {
+        Ok(conf) => {
+            if let Some(path) = get_config_file_loc(explicit) {
+                for diagnostic in validate::check_file(&path) {
+                    eprintln!("{diagnostic}");
+                }
+            }
+            conf
+        }
         Err(r) => {
             eprintln!(
-                "could not create the default config file, using default build in, {}",
+                "could not read the config file, using the built in default, {}",
                 r
             );
             Configuration::default()
@@ -111,7 +600,54 @@ pub fn get_cursors_config_loc() -> Option<std::path::PathBuf> {
     }
 }
 
-pub fn read_config() -> Result<Configuration, Error> {
+/// the directory with the PNG and SVG images of the stamp tool
+pub fn get_stamps_config_loc() -> Option<PathBuf> {
+    let mut conf_path = config_dir()?;
+    conf_path.push(CONFIG_DIR);
+    conf_path.push(CONFIG_STAMPS_DIR);
+    Some(conf_path)
+}
+
+/// the config file that is read, the explicit one or `chicolli.toml` if it exists and
+/// `chicolli.json` otherwise
+pub fn get_config_file_loc(explicit: Option<&Path>) -> Option<PathBuf> {
+    if let Some(explicit) = explicit {
+        return Some(explicit.to_path_buf());
+    }
+    let mut conf_path = config_dir()?;
+    conf_path.push(CONFIG_DIR);
+    let toml_path = conf_path.join(CONFIG_TOML_NAME);
+    if toml_path.exists() {
+        Some(toml_path)
+    } else {
+        Some(conf_path.join(CONFIG_NAME))
+    }
+}
+
+/// every file that can become the config, so creating a `chicolli.toml` next to the json one
+/// is noticed as well
+pub fn get_config_watch_locs(explicit: Option<&Path>) -> Vec<PathBuf> {
+    match explicit {
+        Some(explicit) => vec![explicit.to_path_buf()],
+        None => match config_dir() {
+            Some(mut conf_path) => {
+                conf_path.push(CONFIG_DIR);
+                vec![
+                    conf_path.join(CONFIG_TOML_NAME),
+                    conf_path.join(CONFIG_NAME),
+                ]
+            }
+            None => Vec::new(),
+        },
+    }
+}
+
+/// read the explicit config file, or the one in the config directory. Only in the second case
+/// a default config is written when there is none.
+pub fn read_config(explicit: Option<&Path>) -> Result<Configuration> {
+    if let Some(explicit) = explicit {
+        return read_config_file(explicit);
+    }
     // get the config dir path
     let conf_path = config_dir();
     match conf_path {
@@ -119,39 +655,132 @@ pub fn read_config() -> Result<Configuration, Error> {
             // append the dir name and check if exists
             conf_path.push(CONFIG_DIR);
             if conf_path.as_path().exists() {
+                // a toml config is preferred over the json one
+                let toml_path = conf_path.join(CONFIG_TOML_NAME);
                 // append the name and check if exists
                 conf_path.push(CONFIG_NAME);
-                if conf_path.as_path().exists() {
+                if toml_path.exists() {
+                    read_config_file(toml_path.as_path())
+                } else if conf_path.as_path().exists() {
                     // parse the config and return
-                    let config = read_config_file(conf_path.as_path())?;
-                    return Ok(config);
+                    read_config_file(conf_path.as_path())
                 } else {
-                    write_default_config(conf_path.as_path());
-                    return read_config();
+                    write_default_config(conf_path.as_path())?;
+                    read_config(None)
                 }
             } else {
                 std::fs::create_dir_all(conf_path.as_path())?;
                 conf_path.push(CONFIG_NAME);
-                write_default_config(conf_path.as_path());
-                return read_config();
+                write_default_config(conf_path.as_path())?;
+                read_config(None)
             }
         }
-        None => Err(Error::new(
-            ErrorKind::Other,
-            "counld not find defaul config directory",
-        )),
+        None => Err(Error::NoConfigDir),
     }
 }
 
-fn read_config_file(file_path: &std::path::Path) -> Result<Configuration, Error> {
+/// whether the file is read as toml, everything else is json
+pub fn is_toml(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("toml")
+}
+
+fn read_config_file(file_path: &std::path::Path) -> Result<Configuration> {
     let mut file = File::open(file_path)?;
 
     // Read the content of the file into a string
     let mut content = String::new();
     file.read_to_string(&mut content)?;
 
-    // Deserialize the JSON content into the Configuration struct
-    let config = serde_json::from_str::<Configuration>(&content)?;
+    // Deserialize the content into the Configuration struct, the plain document is kept for
+    // the fields of old configs
+    let (mut config, document) = if is_toml(file_path) {
+        let table = toml::from_str::<toml::Table>(&content)?;
+        let document = serde_json::to_value(&table)?;
+        (Configuration::deserialize(table)?, document)
+    } else {
+        let document = serde_json::from_str::<serde_json::Value>(&content)?;
+        (Configuration::deserialize(&document)?, document)
+    };
+    config.migrate_legacy(&document);
 
-    return Ok(config.merge(Configuration::minimal()));
+    Ok(config.merge(Configuration::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_profiles() -> Configuration {
+        serde_json::from_str(
+            r#"{
+                "line_thickness": 3,
+                "cursor_size": 40,
+                "profiles": {
+                    "teaching": {"line_thickness": 8},
+                    "dark": {"step_style": "letters"}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn profile_over_the_settings() {
+        let config = with_profiles();
+        let teaching = config.with_profile(Some("teaching"));
+        assert_eq!(teaching.line_thickness, Some(8.0));
+        assert_eq!(teaching.cursor_size, Some(40));
+        assert!(teaching.profiles.is_none());
+        // an unknown profile gives the settings without one
+        let unknown = config.with_profile(Some("unknown"));
+        assert_eq!(unknown.line_thickness, Some(3.0));
+        assert!(unknown.profiles.is_none());
+        assert_eq!(config.with_profile(None).line_thickness, Some(3.0));
+    }
+
+    #[test]
+    fn profiles_wrap_around() {
+        let config = with_profiles();
+        let next = |current: Option<&str>, step| config.cycle_profile(current, step);
+        assert_eq!(next(None, 1).as_deref(), Some("dark"));
+        assert_eq!(next(Some("dark"), 1).as_deref(), Some("teaching"));
+        assert_eq!(next(Some("teaching"), 1), None);
+        assert_eq!(next(None, -1).as_deref(), Some("teaching"));
+        assert_eq!(next(Some("dark"), -1), None);
+        // a profile that is gone counts as none
+        assert_eq!(next(Some("gone"), 1).as_deref(), Some("dark"));
+        assert_eq!(Configuration::default().cycle_profile(None, 1), None);
+    }
+
+    fn bindings(entries: &[(&str, &[&str])]) -> Option<BTreeMap<String, KeyList>> {
+        let entries = entries.iter().map(|(action, keys)| {
+            let keys = keys.iter().map(|key| String::from(*key)).collect();
+            (String::from(*action), KeyList::Many(keys))
+        });
+        Some(entries.collect())
+    }
+
+    #[test]
+    fn bindings_override_the_base() {
+        let base = bindings(&[
+            ("tool.pencil", &["1", "p"]),
+            ("tool.arrow", &["2"]),
+            ("color.chooser", &["c"]),
+            ("overlay.quit", &["Escape"]),
+        ]);
+        let config = bindings(&[("tool.arrow", &["p"]), ("overlay.quit", &[])]);
+        let merged = merge_bindings(config, base, &["c"], Accelerator::parse).unwrap();
+        let keys = |action: &str| merged[action].iter().collect::<Vec<_>>();
+        // the listed actions are replaced, an empty list unbinds
+        assert_eq!(keys("tool.arrow"), ["p"]);
+        assert!(keys("overlay.quit").is_empty());
+        // the keys they took, and the keys of palette colors and presets, leave the others
+        assert_eq!(keys("tool.pencil"), ["1"]);
+        assert!(keys("color.chooser").is_empty());
+
+        // without a base the config is taken as it is
+        let config = bindings(&[("tool.arrow", &["3"])]);
+        let merged = merge_bindings(config, None, &[], Accelerator::parse).unwrap();
+        assert_eq!(merged["tool.arrow"].iter().collect::<Vec<_>>(), ["3"]);
+    }
 }
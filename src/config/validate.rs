@@ -0,0 +1,847 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{
+    ArrowConfig, CalloutConfig, Configuration, FillConfig, PaletteColor, Preset, RectangleConfig,
+    SymbolsConfig, LEGACY_FIELDS,
+};
+use crate::colors;
+use crate::keybindings::{self, Action, BindingProblem};
+
+const LINE_WIDTH_RANGE: RangeInclusive<f64> = 0.5..=200.0;
+const CURSOR_SIZE_RANGE: RangeInclusive<i32> = 8..=256;
+const HEAD_LENGTH_RANGE: RangeInclusive<f64> = 1.0..=20.0;
+const HEAD_ANGLE_RANGE: RangeInclusive<f64> = 5.0..=85.0;
+const OPACITY_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+const CORNER_RADIUS_RANGE: RangeInclusive<f64> = 0.0..=200.0;
+
+/// A problem in the config file together with where it was found.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+/// read and check the config file at `path`
+pub fn check_file(path: &Path) -> Vec<Diagnostic> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => check(path, &content),
+        Err(e) => vec![Diagnostic {
+            file: path.to_path_buf(),
+            line: 1,
+            column: 1,
+            message: format!("could not read the config: {e}"),
+        }],
+    }
+}
+
+// what is needed from a parsed config file to check it
+struct Parsed {
+    config: Configuration,
+    // the document as it is written, to find fields serde ignores
+    document: Value,
+    locations: HashMap<Vec<String>, usize>,
+}
+
+// a syntax or type error, line, column and message
+type ParseError = (usize, usize, String);
+
+fn parse_json(content: &str) -> Result<Parsed, ParseError> {
+    // syntax and type errors, serde already knows where they are
+    let config = serde_json::from_str::<Configuration>(content).map_err(|e| {
+        let message = e.to_string();
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) => message.to_string(),
+            None => message,
+        };
+        (e.line(), e.column(), message)
+    })?;
+    Ok(Parsed {
+        config,
+        document: serde_json::from_str(content).unwrap_or_default(),
+        locations: locate_json(content),
+    })
+}
+
+fn parse_toml(content: &str) -> Result<Parsed, ParseError> {
+    let config = toml::from_str::<Configuration>(content).map_err(|e| {
+        let offset = e.span().map(|span| span.start).unwrap_or(0);
+        let (line, column) = line_column(content, offset);
+        (line, column, e.message().to_string())
+    })?;
+    let document = toml::from_str::<toml::Table>(content)
+        .ok()
+        .and_then(|table| serde_json::to_value(table).ok())
+        .unwrap_or_default();
+    Ok(Parsed {
+        config,
+        document,
+        locations: locate_toml(content),
+    })
+}
+
+/// check the content of a config file, the problems come in the order they appear in the file
+pub fn check(path: &Path, content: &str) -> Vec<Diagnostic> {
+    let parsed = if super::is_toml(path) {
+        parse_toml(content)
+    } else {
+        parse_json(content)
+    };
+    let Parsed {
+        config,
+        document,
+        locations,
+    } = match parsed {
+        Ok(parsed) => parsed,
+        Err((line, column, message)) => {
+            return vec![Diagnostic {
+                file: path.to_path_buf(),
+                line,
+                column,
+                message,
+            }]
+        }
+    };
+
+    let mut problems: Vec<(Vec<String>, String)> = Vec::new();
+    check_settings(&config, &document, &[], &mut problems);
+    for (name, profile) in config.profiles.iter().flatten() {
+        let at = vec![String::from("profiles"), name.clone()];
+        if profile.profiles.is_some() {
+            let mut nested = at.clone();
+            nested.push(String::from("profiles"));
+            problems.push((nested, String::from("profiles can not be nested")));
+        }
+        check_settings(profile, &document["profiles"][name], &at, &mut problems);
+    }
+
+    let mut diagnostics: Vec<(usize, Diagnostic)> = problems
+        .into_iter()
+        .map(|(at, message)| {
+            let offset = find(&locations, &at);
+            let (line, column) = line_column(content, offset);
+            let diagnostic = Diagnostic {
+                file: path.to_path_buf(),
+                line,
+                column,
+                message,
+            };
+            (offset, diagnostic)
+        })
+        .collect();
+    diagnostics.sort_by_key(|(offset, _)| *offset);
+    diagnostics
+        .into_iter()
+        .map(|(_, diagnostic)| diagnostic)
+        .collect()
+}
+
+// the problems of the base config or one profile, `at` is where it is in the file
+fn check_settings(
+    config: &Configuration,
+    document: &Value,
+    at: &[String],
+    problems: &mut Vec<(Vec<String>, String)>,
+) {
+    let path = |rest: &[&str]| {
+        let mut path = at.to_vec();
+        path.extend(rest.iter().map(|part| part.to_string()));
+        path
+    };
+
+    let known = fields_of::<Configuration>();
+    if let Value::Object(fields) = document {
+        for name in fields.keys().filter(|name| !known.contains_key(*name)) {
+            let message = match LEGACY_FIELDS.iter().find(|(old, _)| old == name) {
+                Some((_, legacy)) => format!("\"{name}\" is deprecated, move the key to {legacy}"),
+                None => format!("unknown field \"{name}\""),
+            };
+            problems.push((path(&[name]), message));
+        }
+    }
+    let sections = [
+        ("arrow", fields_of::<ArrowConfig>()),
+        ("fill", fields_of::<FillConfig>()),
+        ("rectangle", fields_of::<RectangleConfig>()),
+        ("callout", fields_of::<CalloutConfig>()),
+        ("symbols", fields_of::<SymbolsConfig>()),
+    ];
+    for (section, known) in sections {
+        if let Some(fields) = document.get(section) {
+            unknown_fields(fields, &known, path(&[section]), problems);
+        }
+    }
+    let lists = [
+        ("palette", fields_of::<PaletteColor>()),
+        ("presets", fields_of::<Preset>()),
+    ];
+    for (section, known) in lists {
+        let entries = document.get(section).and_then(Value::as_array);
+        for (index, fields) in entries.into_iter().flatten().enumerate() {
+            unknown_fields(
+                fields,
+                &known,
+                path(&[section, &index.to_string()]),
+                problems,
+            );
+        }
+    }
+
+    if let Some(width) = config.line_thickness {
+        if !LINE_WIDTH_RANGE.contains(&width) {
+            problems.push((
+                path(&["line_thickness"]),
+                format!(
+                    "line_thickness {width} is out of range {}..={}",
+                    LINE_WIDTH_RANGE.start(),
+                    LINE_WIDTH_RANGE.end()
+                ),
+            ));
+        }
+    }
+    if let Some(size) = config.cursor_size {
+        if !CURSOR_SIZE_RANGE.contains(&size) {
+            problems.push((
+                path(&["cursor_size"]),
+                format!(
+                    "cursor_size {size} is out of range {}..={}",
+                    CURSOR_SIZE_RANGE.start(),
+                    CURSOR_SIZE_RANGE.end()
+                ),
+            ));
+        }
+    }
+
+    let mut binding_problems = |section: &str, found: Vec<BindingProblem>| {
+        for problem in found {
+            let mut at = path(&[section, &problem.action]);
+            if let Some(index) = problem.index {
+                at.push(index.to_string());
+            }
+            problems.push((at, problem.message));
+        }
+    };
+    if let Some(bindings) = &config.keybindings {
+        binding_problems("keybindings", keybindings::check_keybindings(bindings));
+    }
+    if let Some(bindings) = &config.mousebindings {
+        binding_problems("mousebindings", keybindings::check_mousebindings(bindings));
+    }
+
+    if let Some(arrow) = &config.arrow {
+        let ranges = [
+            ("head_length", arrow.head_length, HEAD_LENGTH_RANGE),
+            ("head_angle", arrow.head_angle, HEAD_ANGLE_RANGE),
+        ];
+        for (name, value, range) in ranges {
+            match value {
+                Some(value) if !range.contains(&value) => problems.push((
+                    path(&["arrow", name]),
+                    format!(
+                        "{name} {value} is out of range {}..={}",
+                        range.start(),
+                        range.end()
+                    ),
+                )),
+                _ => (),
+            }
+        }
+    }
+
+    if let Some(fill) = &config.fill {
+        if let Some(Err(e)) = fill.color.as_deref().map(colors::parse) {
+            problems.push((path(&["fill", "color"]), e));
+        }
+        if let Some(opacity) = fill.opacity {
+            if !OPACITY_RANGE.contains(&opacity) {
+                problems.push((
+                    path(&["fill", "opacity"]),
+                    format!(
+                        "opacity {opacity} is out of range {}..={}",
+                        OPACITY_RANGE.start(),
+                        OPACITY_RANGE.end()
+                    ),
+                ));
+            }
+        }
+    }
+
+    let corner_radii = [
+        (
+            "rectangle",
+            config.rectangle.as_ref().and_then(|r| r.corner_radius),
+        ),
+        (
+            "callout",
+            config.callout.as_ref().and_then(|c| c.corner_radius),
+        ),
+    ];
+    for (section, radius) in corner_radii {
+        match radius {
+            Some(radius) if !CORNER_RADIUS_RANGE.contains(&radius) => problems.push((
+                path(&[section, "corner_radius"]),
+                format!(
+                    "corner_radius {radius} is out of range {}..={}",
+                    CORNER_RADIUS_RANGE.start(),
+                    CORNER_RADIUS_RANGE.end()
+                ),
+            )),
+            _ => (),
+        }
+    }
+
+    let favorites = config
+        .symbols
+        .iter()
+        .flat_map(|symbols| symbols.favorites.iter().flatten());
+    for (index, favorite) in favorites.enumerate() {
+        if favorite.trim().is_empty() {
+            problems.push((
+                path(&["symbols", "favorites", &index.to_string()]),
+                String::from("a favorite symbol can not be empty"),
+            ));
+        }
+    }
+
+    for (index, entry) in config.palette.iter().flatten().enumerate() {
+        if let Err(e) = colors::parse(&entry.color) {
+            problems.push((path(&["palette", &index.to_string(), "color"]), e));
+        }
+    }
+    for (index, preset) in config.presets.iter().flatten().enumerate() {
+        let at = |field: &str| path(&["presets", &index.to_string(), field]);
+        if let Some(tool) = &preset.tool {
+            if Action::tool_by_name(tool).is_none() {
+                problems.push((at("tool"), format!("unknown tool \"{tool}\"")));
+            }
+        }
+        if let Some(Err(e)) = preset.color.as_deref().map(colors::parse) {
+            problems.push((at("color"), e));
+        }
+        if let Some(width) = preset.width {
+            if !LINE_WIDTH_RANGE.contains(&width) {
+                problems.push((
+                    at("width"),
+                    format!(
+                        "width {width} is out of range {}..={}",
+                        LINE_WIDTH_RANGE.start(),
+                        LINE_WIDTH_RANGE.end()
+                    ),
+                ));
+            }
+        }
+    }
+    for (section, index, message) in keybindings::check_entry_keys(config) {
+        problems.push((path(&[section, &index.to_string(), "key"]), message));
+    }
+}
+
+// every field of a section shows up when serializing it
+fn fields_of<T: Default + Serialize>() -> Map<String, Value> {
+    match serde_json::to_value(T::default()) {
+        Ok(Value::Object(known)) => known,
+        _ => Map::new(),
+    }
+}
+
+fn unknown_fields(
+    fields: &Value,
+    known: &Map<String, Value>,
+    at: Vec<String>,
+    problems: &mut Vec<(Vec<String>, String)>,
+) {
+    if let Value::Object(fields) = fields {
+        for name in fields.keys().filter(|name| !known.contains_key(*name)) {
+            let mut at = at.clone();
+            at.push(name.clone());
+            problems.push((at, format!("unknown field \"{name}\"")));
+        }
+    }
+}
+
+// the offset of the deepest part of `path` that is in the file
+fn find(locations: &HashMap<Vec<String>, usize>, path: &[String]) -> usize {
+    (1..=path.len())
+        .rev()
+        .find_map(|len| locations.get(&path[..len]).copied())
+        .unwrap_or(0)
+}
+
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = match before.rfind('\n') {
+        Some(newline) => before[newline + 1..].chars().count() + 1,
+        None => before.chars().count() + 1,
+    };
+    (line, column)
+}
+
+/// Byte offset of every object key and array element of a JSON document by its path, array
+/// elements use their index as path segment. Only used on documents serde already parsed.
+fn locate_json(content: &str) -> HashMap<Vec<String>, usize> {
+    let mut scanner = Scanner {
+        text: content.as_bytes(),
+        content,
+        pos: 0,
+        locations: HashMap::new(),
+    };
+    scanner.value(&mut Vec::new());
+    scanner.locations
+}
+
+struct Scanner<'a> {
+    text: &'a [u8],
+    content: &'a str,
+    pos: usize,
+    locations: HashMap<Vec<String>, usize>,
+}
+
+impl Scanner<'_> {
+    fn peek(&mut self) -> Option<u8> {
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        self.text.get(self.pos).copied()
+    }
+
+    fn value(&mut self, path: &mut Vec<String>) -> Option<()> {
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                loop {
+                    match self.peek()? {
+                        b'}' => break,
+                        b',' => self.pos += 1,
+                        _ => {
+                            let start = self.pos;
+                            path.push(self.string()?);
+                            self.locations.insert(path.clone(), start);
+                            // the colon
+                            self.peek()?;
+                            self.pos += 1;
+                            self.value(path)?;
+                            path.pop();
+                        }
+                    }
+                }
+                self.pos += 1;
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut index = 0;
+                loop {
+                    match self.peek()? {
+                        b']' => break,
+                        b',' => self.pos += 1,
+                        _ => {
+                            path.push(index.to_string());
+                            self.locations.insert(path.clone(), self.pos);
+                            self.value(path)?;
+                            path.pop();
+                            index += 1;
+                        }
+                    }
+                }
+                self.pos += 1;
+            }
+            b'"' => {
+                self.string()?;
+            }
+            // numbers, true, false and null
+            _ => {
+                while self
+                    .text
+                    .get(self.pos)
+                    .is_some_and(|b| !b.is_ascii_whitespace() && !b",]}".contains(b))
+                {
+                    self.pos += 1;
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.text.get(self.pos)? {
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        serde_json::from_str(&self.content[start..self.pos]).ok()
+    }
+}
+
+/// Byte offset of every key and string array element of a TOML document by its path, like
+/// `locate_json`. Only understands what a config uses: tables, arrays of tables, (dotted) keys
+/// and arrays of strings.
+fn locate_toml(content: &str) -> HashMap<Vec<String>, usize> {
+    let mut locations = HashMap::new();
+    let mut table: Vec<String> = Vec::new();
+    // how many elements of each array of tables were seen
+    let mut tables: HashMap<Vec<String>, usize> = HashMap::new();
+    // an array that continues on the next lines, its path and the next index
+    let mut array: Option<(Vec<String>, usize)> = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let start = offset + (line.len() - line.trim_start().len());
+        let text = line.trim();
+        offset += line.len();
+
+        if let Some((path, index)) = &mut array {
+            if scan_toml_array(line, offset - line.len(), path, index, &mut locations) {
+                array = None;
+            }
+        } else if let Some(header) = text.strip_prefix("[[") {
+            // every [[array of tables]] header is the next element of that array
+            if let Some((key, _)) = toml_key(header) {
+                locations.entry(key.clone()).or_insert(start);
+                let index = tables.entry(key.clone()).or_insert(0);
+                table = [key, vec![index.to_string()]].concat();
+                *index += 1;
+                locations.insert(table.clone(), start);
+            }
+        } else if let Some(header) = text.strip_prefix('[') {
+            if let Some((key, _)) = toml_key(header) {
+                table = key;
+                locations.insert(table.clone(), start);
+            }
+        } else if let Some((key, rest)) = toml_key(text) {
+            let Some(value) = rest.trim_start().strip_prefix('=') else {
+                continue;
+            };
+            let path = [table.clone(), key].concat();
+            locations.insert(path.clone(), start);
+
+            let value = value.trim_start();
+            if value.starts_with('[') {
+                // the value ends where the trimmed line ends
+                let value_start = offset - line.len() + line.trim_end().len() - value.len();
+                let mut index = 0;
+                if !scan_toml_array(
+                    &content[value_start + 1..offset],
+                    value_start + 1,
+                    &path,
+                    &mut index,
+                    &mut locations,
+                ) {
+                    array = Some((path, index));
+                }
+            }
+        }
+    }
+    locations
+}
+
+// the segments of a (dotted, maybe quoted) key at the start of `text` and the text after it
+fn toml_key(text: &str) -> Option<(Vec<String>, &str)> {
+    let mut segments = Vec::new();
+    let mut rest = text.trim_start();
+    loop {
+        let (segment, after) = match rest.chars().next()? {
+            '"' | '\'' => {
+                let end = string_end(rest)?;
+                let quoted = &rest[..end];
+                let segment = if quoted.starts_with('"') {
+                    serde_json::from_str(quoted).ok()?
+                } else {
+                    quoted[1..quoted.len() - 1].to_string()
+                };
+                (segment, &rest[end..])
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                    .unwrap_or(rest.len());
+                if end == 0 {
+                    return None;
+                }
+                (rest[..end].to_string(), &rest[end..])
+            }
+        };
+        segments.push(segment);
+        rest = after.trim_start();
+        match rest.strip_prefix('.') {
+            Some(after) => rest = after.trim_start(),
+            None => return Some((segments, rest)),
+        }
+    }
+}
+
+// the byte after the closing quote of the string `text` starts with
+fn string_end(text: &str) -> Option<usize> {
+    let quote = text.as_bytes()[0];
+    let mut pos = 1;
+    let bytes = text.as_bytes();
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' if quote == b'"' => pos += 2,
+            b if b == quote => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+// record the strings in `text`, which starts at `offset` inside an array, true once the array
+// is closed
+fn scan_toml_array(
+    text: &str,
+    offset: usize,
+    path: &[String],
+    index: &mut usize,
+    locations: &mut HashMap<Vec<String>, usize>,
+) -> bool {
+    let mut pos = 0;
+    while pos < text.len() {
+        match text.as_bytes()[pos] {
+            b'"' | b'\'' => {
+                let mut element = path.to_vec();
+                element.push(index.to_string());
+                locations.insert(element, offset + pos);
+                *index += 1;
+                match string_end(&text[pos..]) {
+                    Some(end) => pos += end,
+                    None => return false,
+                }
+            }
+            b']' => return true,
+            b'#' => return false,
+            _ => pos += 1,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|part| part.to_string()).collect()
+    }
+
+    // the text from the location of `parts` on
+    fn at<'a>(
+        content: &'a str,
+        locations: &HashMap<Vec<String>, usize>,
+        parts: &[&str],
+    ) -> &'a str {
+        &content[locations[&path(parts)]..]
+    }
+
+    fn diagnostic(file: &str, line: usize, column: usize, message: &str) -> Diagnostic {
+        Diagnostic {
+            file: PathBuf::from(file),
+            line,
+            column,
+            message: String::from(message),
+        }
+    }
+
+    #[test]
+    fn check_json() {
+        let content = "{\n  \"line_thickness\": 500,\n  \"draw_keybind\": \"p\",\n  \
+            \"keybindings\": {\"tool.arrow\": \"x\", \"tool.pencil\": \"x\"},\n  \
+            \"arrow\": {\"head_lenght\": 3}\n}\n";
+        assert_eq!(
+            check(Path::new("config.json"), content),
+            [
+                diagnostic(
+                    "config.json",
+                    2,
+                    3,
+                    "line_thickness 500 is out of range 0.5..=200"
+                ),
+                diagnostic(
+                    "config.json",
+                    3,
+                    3,
+                    "\"draw_keybind\" is deprecated, move the key to \"tool.pencil\" in keybindings"
+                ),
+                diagnostic(
+                    "config.json",
+                    4,
+                    38,
+                    "\"x\" is already bound to tool.arrow, ignoring this binding"
+                ),
+                diagnostic("config.json", 5, 13, "unknown field \"head_lenght\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_toml() {
+        let content = "cursor_size = 4\n\
+            [keybindings]\n\
+            \"tool.arrow\" = \"x\"\n\
+            \"tool.pencil\" = [\"y\", \"x\"]\n\
+            [[palette]]\n\
+            name = \"red\"\n\
+            color = \"red\"\n\
+            shade = 1\n";
+        assert_eq!(
+            check(Path::new("config.toml"), content),
+            [
+                diagnostic("config.toml", 1, 1, "cursor_size 4 is out of range 8..=256"),
+                diagnostic(
+                    "config.toml",
+                    4,
+                    23,
+                    "\"x\" is already bound to tool.arrow, ignoring this binding"
+                ),
+                diagnostic("config.toml", 8, 1, "unknown field \"shade\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_syntax_error() {
+        let content = "{\n  \"line_thickness\": \"thick\"\n}\n";
+        assert_eq!(
+            check(Path::new("config.json"), content),
+            [diagnostic(
+                "config.json",
+                2,
+                27,
+                "invalid type: string \"thick\", expected f64"
+            )]
+        );
+    }
+
+    #[test]
+    fn json_nested_keys_and_elements() {
+        let content = r#"{
+            "keybindings": {"tool.pencil": ["1", "<Shift>1"]},
+            "palette": [{"name": "red"}, {"name": "blue", "color": "\"x"}],
+            "a\"b": {"c": null}
+        }"#;
+        let locations = locate_json(content);
+        let at = |parts: &[&str]| at(content, &locations, parts);
+        assert!(at(&["keybindings"]).starts_with(r#""keybindings""#));
+        assert!(at(&["keybindings", "tool.pencil"]).starts_with(r#""tool.pencil""#));
+        assert!(at(&["keybindings", "tool.pencil", "1"]).starts_with(r#""<Shift>1""#));
+        assert!(at(&["palette", "1"]).starts_with(r#"{"name": "blue""#));
+        assert!(at(&["palette", "1", "color"]).starts_with(r#""color""#));
+        assert!(at(&["a\"b", "c"]).starts_with(r#""c""#));
+    }
+
+    #[test]
+    fn toml_tables_and_dotted_keys() {
+        let content = "line_thickness = 3\n\
+            arrow.head_length = 4\n\
+            [profiles.teaching]\n\
+            \"line_thickness\" = 6\n\
+            [[palette]]\n\
+            name = 'red'\n\
+            [[palette]]\n\
+            color = \"blue\"\n";
+        let locations = locate_toml(content);
+        let at = |parts: &[&str]| at(content, &locations, parts);
+        assert!(at(&["line_thickness"]).starts_with("line_thickness = 3"));
+        assert!(at(&["arrow", "head_length"]).starts_with("arrow.head_length"));
+        assert!(at(&["profiles", "teaching"]).starts_with("[profiles.teaching]"));
+        assert!(
+            at(&["profiles", "teaching", "line_thickness"]).starts_with("\"line_thickness\" = 6")
+        );
+        assert!(at(&["palette", "0", "name"]).starts_with("name = 'red'"));
+        assert!(at(&["palette", "1"]).starts_with("[[palette]]\ncolor"));
+        assert!(at(&["palette", "1", "color"]).starts_with("color = \"blue\""));
+    }
+
+    #[test]
+    fn toml_multi_line_arrays() {
+        let content = "[keybindings]\n\
+            \"tool.arrow\" = [\"2\",\n\
+            \x20   \"<Shift>2\", # \"not an element\"\n\
+            \x20   '<Ctrl>2',\n\
+            ]\n\
+            \"tool.pencil\" = [\"1\"]\n";
+        let locations = locate_toml(content);
+        let at = |parts: &[&str]| at(content, &locations, parts);
+        assert!(at(&["keybindings", "tool.arrow", "0"]).starts_with("\"2\""));
+        assert!(at(&["keybindings", "tool.arrow", "1"]).starts_with("\"<Shift>2\""));
+        assert!(at(&["keybindings", "tool.arrow", "2"]).starts_with("'<Ctrl>2'"));
+        assert!(!locations.contains_key(&path(&["keybindings", "tool.arrow", "3"])));
+        assert!(at(&["keybindings", "tool.pencil", "0"]).starts_with("\"1\"]"));
+    }
+
+    #[test]
+    fn toml_key_segments() {
+        assert_eq!(
+            toml_key(r#"a . "b.c".'d' = 1"#),
+            Some((path(&["a", "b.c", "d"]), "= 1"))
+        );
+        assert_eq!(toml_key(r#""a\"b" = 1"#), Some((path(&["a\"b"]), "= 1")));
+        assert_eq!(toml_key("= 1"), None);
+        assert_eq!(toml_key("\"open = 1"), None);
+    }
+
+    #[test]
+    fn toml_array_elements() {
+        let mut locations = HashMap::new();
+        let mut index = 0;
+        let closed = scan_toml_array(
+            r#""a", 'b]' ]"#,
+            10,
+            &path(&["k"]),
+            &mut index,
+            &mut locations,
+        );
+        assert!(closed);
+        assert_eq!(index, 2);
+        assert_eq!(locations[&path(&["k", "0"])], 10);
+        assert_eq!(locations[&path(&["k", "1"])], 15);
+
+        // a comment or the end of the line leave the array open
+        let mut index = 0;
+        assert!(!scan_toml_array(
+            r#""a", # "b""#,
+            0,
+            &path(&["k"]),
+            &mut index,
+            &mut locations
+        ));
+        assert_eq!(index, 1);
+        assert!(!scan_toml_array(
+            "\"a\",\n",
+            0,
+            &path(&["k"]),
+            &mut index,
+            &mut locations
+        ));
+        assert_eq!(index, 2);
+    }
+}
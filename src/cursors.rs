@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
+
+use gtk::cairo::{Context, Format, ImageSurface};
+use gtk::gdk::{prelude::GdkCairoContextExt, Cursor, MemoryFormat, MemoryTexture};
+use gtk::gdk_pixbuf::Pixbuf;
+use gtk::glib;
+use serde::Deserialize;
+
+use crate::colors::Color;
+use crate::config;
+use crate::drawing::drawing_tool::{Bounds, CurrentDrawingTool, Point};
+use crate::error::Result;
+
+// cairo stores ARGB32 as native endian 32 bit values
+#[cfg(target_endian = "little")]
+const CAIRO_MEMORY_FORMAT: MemoryFormat = MemoryFormat::B8g8r8a8Premultiplied;
+#[cfg(target_endian = "big")]
+const CAIRO_MEMORY_FORMAT: MemoryFormat = MemoryFormat::A8r8g8b8Premultiplied;
+
+// very thick lines would make the cursor huge, the brush circle stops growing here
+const MAX_BRUSH_RADIUS: f64 = 64.0;
+
+/// Entry of the cursors manifest, e.g.
+/// `{ "pencil": { "file": "pencil.svg", "size": 32, "hotspot": [0, 32] } }`.
+/// The hotspot is in logical pixels from the top left corner of the icon.
+#[derive(Debug, Default, Deserialize)]
+struct ManifestEntry {
+    file: Option<String>,
+    size: Option<i32>,
+    hotspot: Option<(f64, f64)>,
+}
+
+struct Icon {
+    pixbuf: Pixbuf,
+    hotspot: Point,
+}
+
+/// The tool icons from the config directory. The actual cursor is generated from them together
+/// with the current color and line width.
+///
+/// Everything is in logical pixels. A texture cursor has no buffer scale before GTK 4.16, a
+/// texture with the pixels of the output scale would show up that many times too big, so on
+/// HiDPI outputs the cursors are upscaled by the compositor and look soft.
+#[derive(Default)]
+pub struct Cursors {
+    icons: HashMap<&'static str, Icon>,
+}
+
+impl Cursors {
+    /// load the tool icons from the config directory, `size` is in logical pixels. Files that
+    /// can not be loaded are reported and skipped.
+    pub fn load(size: i32) -> Cursors {
+        let mut cursors = Cursors::default();
+
+        let Some(curs_loc) = config::get_cursors_config_loc() else {
+            return cursors;
+        };
+        let mut manifest = read_manifest(&curs_loc.join(config::CURSORS_MANIFEST));
+
+        for name in [config::PENCIL_CUR, config::ARROW_CUR, config::SQUARE_CUR] {
+            let entry = manifest.remove(name).unwrap_or_default();
+            let path = match &entry.file {
+                Some(file) => Some(curs_loc.join(file)),
+                None => find_icon_file(&curs_loc, name),
+            };
+            let Some(path) = path else {
+                continue;
+            };
+
+            let size = entry.size.unwrap_or(size);
+            match Pixbuf::from_file_at_scale(&path, size, size, true) {
+                Ok(pixbuf) => {
+                    let hotspot = match entry.hotspot {
+                        Some((x, y)) => Point(x, y),
+                        None => default_hotspot(name, &pixbuf),
+                    };
+                    cursors.icons.insert(name, Icon { pixbuf, hotspot });
+                }
+                Err(e) => eprintln!("could not load cursor {}: {}", path.display(), e),
+            }
+        }
+        cursors
+    }
+
+    fn icon(&self, tool: &CurrentDrawingTool) -> Option<&Icon> {
+        let name = match tool {
+            CurrentDrawingTool::NormalLine => config::PENCIL_CUR,
+            CurrentDrawingTool::NormalArrowHeadBase
+            | CurrentDrawingTool::NormalArrowHeadPointer
+            | CurrentDrawingTool::CurvedArrow => config::ARROW_CUR,
+            CurrentDrawingTool::NormalRectangle
+            | CurrentDrawingTool::Polyline
+            | CurrentDrawingTool::Callout
+            | CurrentDrawingTool::StepBadge
+            | CurrentDrawingTool::Stamp => config::SQUARE_CUR,
+        };
+        self.icons.get(name)
+    }
+
+    /// the cursor for `tool`: a circle as big as the next stroke in its color, with the tool
+    /// icon as a badge next to it. The hotspot is the center of the circle.
+    pub fn for_tool(
+        &self,
+        tool: &CurrentDrawingTool,
+        color: Color,
+        line_width: f64,
+    ) -> Option<Cursor> {
+        match self.render(tool, color, line_width) {
+            Ok(cursor) => Some(cursor),
+            Err(e) => {
+                eprintln!("could not render cursor: {e}");
+                Cursor::from_name("default", None)
+            }
+        }
+    }
+
+    fn render(&self, tool: &CurrentDrawingTool, color: Color, line_width: f64) -> Result<Cursor> {
+        // everything is around the center of the brush circle
+        let outline = 1.0;
+        let radius = (line_width / 2.0).clamp(1.0, MAX_BRUSH_RADIUS);
+        let circle = Bounds {
+            min: Point(-radius - outline, -radius - outline),
+            max: Point(radius + outline, radius + outline),
+        };
+
+        // the hotspot of the icon touches the circle on the side opposite to the icon body, so a
+        // pencil with its tip in the bottom left corner sits on the upper right of the circle
+        let icon = self.icon(tool).map(|icon| {
+            let size = Point(icon.pixbuf.width() as f64, icon.pixbuf.height() as f64);
+            let direction = icon.hotspot - size / 2.0;
+            let length = direction.0.hypot(direction.1);
+            let attach = if length > f64::EPSILON {
+                -direction * ((radius + outline) / length)
+            } else {
+                Point(0.0, 0.0)
+            };
+            let origin = attach - icon.hotspot;
+            (icon, origin, Bounds::from_points(&[origin, origin + size]))
+        });
+
+        let bounds = match &icon {
+            Some((_, _, Some(icon_bounds))) => circle.union(*icon_bounds),
+            _ => circle,
+        };
+        let width = bounds.width().ceil() as i32;
+        let height = bounds.height().ceil() as i32;
+
+        let surface = ImageSurface::create(Format::ARgb32, width, height)?;
+        {
+            let ctx = Context::new(&surface)?;
+            ctx.translate(-bounds.min.0, -bounds.min.1);
+            ctx.arc(0.0, 0.0, radius, 0.0, 2.0 * PI);
+            ctx.set_source_rgba(
+                color.red().into(),
+                color.green().into(),
+                color.blue().into(),
+                color.alpha().into(),
+            );
+            ctx.fill_preserve()?;
+            // a thin dark outline keeps the circle visible on any background
+            ctx.set_source_rgba(0.0, 0.0, 0.0, 0.6);
+            ctx.set_line_width(outline);
+            ctx.stroke()?;
+
+            if let Some((icon, origin, _)) = icon {
+                ctx.set_source_pixbuf(&icon.pixbuf, origin.0, origin.1);
+                ctx.paint()?;
+            }
+        }
+        surface.flush();
+
+        let stride = surface.stride() as usize;
+        let data = surface.take_data()?;
+        let texture = MemoryTexture::new(
+            width,
+            height,
+            CAIRO_MEMORY_FORMAT,
+            &glib::Bytes::from(&*data),
+            stride,
+        );
+        Ok(Cursor::from_texture(
+            &texture,
+            (-bounds.min.0).round() as i32,
+            (-bounds.min.1).round() as i32,
+            None,
+        ))
+    }
+}
+
+fn read_manifest(path: &Path) -> HashMap<String, ManifestEntry> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        // the manifest is optional
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return HashMap::new(),
+        Err(e) => {
+            eprintln!("could not read {}: {}", path.display(), e);
+            return HashMap::new();
+        }
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("invalid cursor manifest {}: {}", path.display(), e);
+        HashMap::new()
+    })
+}
+
+// without a manifest entry the icon is the file named after the cursor, an svg is preferred
+// since it scales without getting blurry
+fn find_icon_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    let mut found = None;
+    for path in std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+    {
+        if path.file_stem().and_then(|stem| stem.to_str()) != Some(name) {
+            continue;
+        }
+        let is_svg = path.extension().and_then(|ext| ext.to_str()) == Some("svg");
+        if is_svg || found.is_none() {
+            found = Some(path);
+        }
+        if is_svg {
+            break;
+        }
+    }
+    found
+}
+
+// the shipped pencil has its tip in the bottom left corner, the other icons point to the top left
+fn default_hotspot(name: &str, pixbuf: &Pixbuf) -> Point {
+    match name {
+        config::PENCIL_CUR => Point(0.0, pixbuf.height() as f64),
+        _ => Point(0.0, 0.0),
+    }
+}
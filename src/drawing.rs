@@ -1,4 +1,13 @@
 pub mod arrow;
+pub mod arrow_head;
+pub mod backing;
+pub mod callout;
+pub mod curved_arrow;
 pub mod drawing_tool;
+pub mod fill;
+pub mod line_style;
 pub mod normal_line;
 pub mod normal_rectangle;
+pub mod polyline;
+pub mod stamp;
+pub mod step_badge;
@@ -1,108 +1,112 @@
+use gtk::gdk::ModifierType;
+
 use crate::colors::{self, Color};
+use crate::error::Result;
 
-use super::drawing_tool::{DrawingTool, Point};
+use super::arrow_head::ArrowHeads;
+use super::drawing_tool::{drag_ends, draw_translucent, snap_angle, Bounds, DrawingTool, Point};
+use super::line_style::LineStyle;
 
 pub struct NormalArrow {
+    // where the drag started, the start unless the arrow is drawn from its center
+    anchor: Option<Point>,
+    // held during the last motion, the release event does not carry them
+    modifiers: ModifierType,
     start: Option<Point>,
     end: Option<Point>,
-    arrow_length: f64,
-    arrow_degree: f64,
+    heads: ArrowHeads,
     arrow_width: f64,
+    line_style: LineStyle,
     finished: bool,
     direction_head_base: bool,
     color: Color,
 }
 
 impl NormalArrow {
+    /// `direction` swaps the heads, for the reverse arrow
     pub fn new(direction: bool) -> NormalArrow {
         NormalArrow {
+            anchor: None,
+            modifiers: ModifierType::empty(),
             start: None,
             end: None,
-            arrow_length: 20.0,
-            arrow_degree: 0.58067840828,
+            heads: ArrowHeads::default(),
             arrow_width: 2.0,
+            line_style: LineStyle::Solid,
             finished: false,
             direction_head_base: direction,
             color: colors::RED,
         }
     }
+
+    // Shift snaps the angle, Alt draws from the center
+    fn drag_to(&mut self, point: Point, modifiers: ModifierType) {
+        if let Some((start, end)) = drag_ends(self.anchor, point, modifiers, snap_angle) {
+            self.start = Some(start);
+            self.end = Some(end);
+            self.modifiers = modifiers;
+        }
+    }
+
+    // the shaft and the heads, in the current source
+    fn draw_arrow(&self, cnx: &gtk::cairo::Context, start: Point, end: Point) -> Result<()> {
+        let (start_head, end_head) = match self.direction_head_base {
+            true => (self.heads.end, self.heads.start),
+            false => (self.heads.start, self.heads.end),
+        };
+        let angle_main_line = (end.1 - start.1).atan2(end.0 - start.0);
+        let direction = Point(angle_main_line.cos(), angle_main_line.sin());
+
+        // the shaft stops short of heads that would get blunted by it
+        let shaft_start = start + direction * self.heads.inset(start_head, self.arrow_width);
+        let shaft_end = end - direction * self.heads.inset(end_head, self.arrow_width);
+        cnx.move_to(shaft_start.0, shaft_start.1);
+        cnx.line_to(shaft_end.0, shaft_end.1);
+        self.line_style.stroke(cnx)?;
+
+        self.heads.draw(
+            cnx,
+            start_head,
+            start,
+            angle_main_line + std::f64::consts::PI,
+            self.arrow_width,
+        )?;
+        self.heads
+            .draw(cnx, end_head, end, angle_main_line, self.arrow_width)?;
+        Ok(())
+    }
 }
 
 impl DrawingTool for NormalArrow {
     fn release_mouse(&mut self, point: super::drawing_tool::Point) {
-        self.end = Some(point);
+        self.drag_to(point, self.modifiers);
         self.finished = true;
     }
 
-    fn press_mouse(&mut self, point: super::drawing_tool::Point) {
+    fn press_mouse(&mut self, point: super::drawing_tool::Point, modifiers: ModifierType) {
+        self.anchor = Some(point);
         self.start = Some(point);
+        self.modifiers = modifiers;
     }
 
-    fn motion_notify(&mut self, point: super::drawing_tool::Point) {
+    fn motion_notify(&mut self, point: super::drawing_tool::Point, modifiers: ModifierType) {
         if !self.finished {
-            self.end = Some(point)
+            self.drag_to(point, modifiers);
         }
     }
 
-    fn draw(&self, cnx: &gtk::cairo::Context) {
+    fn draw(&self, cnx: &gtk::cairo::Context) -> Result<()> {
         if let (Some(start), Some(end)) = (self.start, self.end) {
-            let color = self.color;
-            cnx.set_source_rgb(
-                color.red().into(),
-                color.green().into(),
-                color.blue().into(),
-            );
-            cnx.set_line_cap(gtk::cairo::LineCap::Round);
-            cnx.set_line_join(gtk::cairo::LineJoin::Round);
-            cnx.set_line_width(self.arrow_width);
-            cnx.move_to(start.0, start.1);
-            cnx.line_to(end.0, end.1);
-
-            let angle_main_line = (end.1 - start.1).atan2(end.0 - start.0);
-
-            // the tips of the arrow lines
-            let (mut x1, mut y1): (f64, f64);
-            let (mut x2, mut y2): (f64, f64);
-
-            x1 = self.arrow_length * (angle_main_line - self.arrow_degree).cos();
-            y1 = self.arrow_length * (angle_main_line - self.arrow_degree).sin();
-            x2 = self.arrow_length * (angle_main_line + self.arrow_degree).cos();
-            y2 = self.arrow_length * (angle_main_line + self.arrow_degree).sin();
-
-            match self.direction_head_base {
-                true => {
-                    x1 += start.0;
-                    y1 += start.1;
-                    x2 += start.0;
-                    y2 += start.1;
-                }
-                false => {
-                    x1 = end.0 - x1;
-                    y1 = end.1 - y1;
-                    x2 = end.0 - x2;
-                    y2 = end.1 - y2;
-                }
-            }
-
-            match self.direction_head_base {
-                true => cnx.move_to(start.0, start.1),
-                false => cnx.move_to(end.0, end.1),
-            }
-
-            cnx.line_to(x1, y1);
-
-            match self.direction_head_base {
-                true => cnx.move_to(start.0, start.1),
-                false => cnx.move_to(end.0, end.1),
-            }
-
-            cnx.line_to(x2, y2);
-
-            match cnx.stroke() {
-                Err(e) => println!("{e}"),
-                _ => (),
-            }
+            // shaft and heads overlap, they are blended together so a translucent arrow does
+            // not get darker where they meet
+            draw_translucent(cnx, self.bounds(), self.color, || {
+                cnx.set_line_cap(gtk::cairo::LineCap::Round);
+                cnx.set_line_join(gtk::cairo::LineJoin::Round);
+                cnx.set_line_width(self.arrow_width);
+                self.draw_arrow(cnx, start, end)
+            })?;
         }
+        Ok(())
     }
 
     fn set_line_width(&mut self, width: f64) {
@@ -113,7 +117,25 @@ impl DrawingTool for NormalArrow {
         self.color = color;
     }
 
-    fn active(&mut self) -> bool {
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.line_style = style;
+    }
+
+    fn set_arrow_heads(&mut self, heads: ArrowHeads) {
+        self.heads = heads;
+    }
+
+    fn active(&self) -> bool {
         return self.start.is_some() && !self.finished;
     }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let (start, end) = (self.start?, self.end?);
+        // the head can point anywhere around the tip, so grow by its full length
+        let head = self.heads.size(self.arrow_width);
+        Some(
+            Bounds::from_points(&[start, end])?
+                .grow(head + self.line_style.width(self.arrow_width)),
+        )
+    }
 }
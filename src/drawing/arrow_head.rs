@@ -0,0 +1,135 @@
+use std::f64::consts::PI;
+
+use gtk::cairo::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+use super::drawing_tool::Point;
+
+/// the angle between the shaft and each side of the head of the original arrows
+pub const DEFAULT_HEAD_ANGLE: f64 = 0.58067840828;
+/// head length in line widths
+pub const DEFAULT_HEAD_LENGTH: f64 = 5.0;
+// thin lines would get a head too small to see
+const MIN_HEAD_LENGTH: f64 = 10.0;
+
+/// What one end of an arrow looks like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadStyle {
+    /// two lines, the original arrow
+    Open,
+    Triangle,
+    Circle,
+    Diamond,
+    /// a line across the end
+    Bar,
+    None,
+}
+
+/// The heads of the arrow tools. The arrow tool puts `end` where the mouse is released, the
+/// reverse arrow swaps the ends.
+#[derive(Debug, Clone, Copy)]
+pub struct ArrowHeads {
+    pub start: HeadStyle,
+    pub end: HeadStyle,
+    /// in line widths
+    pub length: f64,
+    /// in radians
+    pub angle: f64,
+}
+
+impl Default for ArrowHeads {
+    fn default() -> Self {
+        ArrowHeads {
+            start: HeadStyle::None,
+            end: HeadStyle::Open,
+            length: DEFAULT_HEAD_LENGTH,
+            angle: DEFAULT_HEAD_ANGLE,
+        }
+    }
+}
+
+impl ArrowHeads {
+    /// the length of a head in pixels, it grows with the line
+    pub fn size(&self, line_width: f64) -> f64 {
+        (self.length * line_width).max(MIN_HEAD_LENGTH)
+    }
+
+    /// how far the shaft has to stop before the tip, a round line cap would blunt the point
+    /// of a filled triangle
+    pub fn inset(&self, style: HeadStyle, line_width: f64) -> f64 {
+        match style {
+            HeadStyle::Triangle => self.size(line_width) * self.angle.cos() / 2.0,
+            _ => 0.0,
+        }
+    }
+
+    /// draw a head at `tip` for a shaft arriving in the direction `angle`, in the current
+    /// source and line width
+    pub fn draw(
+        &self,
+        cnx: &Context,
+        style: HeadStyle,
+        tip: Point,
+        angle: f64,
+        line_width: f64,
+    ) -> Result<()> {
+        let size = self.size(line_width);
+        // points relative to the tip, `along` backwards on the shaft and `across` to its left
+        let at = |along: f64, across: f64| {
+            let (sin, cos) = angle.sin_cos();
+            Point(
+                tip.0 - along * cos - across * sin,
+                tip.1 - along * sin + across * cos,
+            )
+        };
+        let back = size * self.angle.cos();
+        let side = size * self.angle.sin();
+
+        match style {
+            HeadStyle::Open => {
+                let (left, right) = (at(back, side), at(back, -side));
+                cnx.move_to(left.0, left.1);
+                cnx.line_to(tip.0, tip.1);
+                cnx.line_to(right.0, right.1);
+                cnx.stroke()?;
+            }
+            HeadStyle::Triangle => {
+                let (left, right) = (at(back, side), at(back, -side));
+                cnx.move_to(tip.0, tip.1);
+                cnx.line_to(left.0, left.1);
+                cnx.line_to(right.0, right.1);
+                cnx.close_path();
+                cnx.fill()?;
+            }
+            HeadStyle::Circle => {
+                cnx.arc(tip.0, tip.1, (size / 3.0).max(line_width), 0.0, 2.0 * PI);
+                cnx.fill()?;
+            }
+            HeadStyle::Diamond => {
+                let points = [
+                    at(-back / 2.0, 0.0),
+                    at(0.0, side / 2.0),
+                    at(back / 2.0, 0.0),
+                    at(0.0, -side / 2.0),
+                ];
+                cnx.move_to(points[0].0, points[0].1);
+                for point in &points[1..] {
+                    cnx.line_to(point.0, point.1);
+                }
+                cnx.close_path();
+                cnx.fill()?;
+            }
+            HeadStyle::Bar => {
+                let (left, right) = (at(0.0, side), at(0.0, -side));
+                cnx.move_to(left.0, left.1);
+                cnx.line_to(right.0, right.1);
+                cnx.stroke()?;
+            }
+            HeadStyle::None => (),
+        }
+        Ok(())
+    }
+}
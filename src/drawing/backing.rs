@@ -0,0 +1,89 @@
+use gtk::cairo::{Context, Format, ImageSurface};
+
+use super::drawing_tool::{saved, DrawingTool};
+use crate::error::Result;
+
+/// Offscreen surface with the finished elements already rasterized, so a redraw only has to
+/// paint one image instead of replaying the whole history.
+pub struct Backing {
+    surface: Option<ImageSurface>,
+    // scale the surface was rasterized at, on HiDPI and fractionally scaled outputs the surface
+    // has more pixels than the widget
+    scale: f64,
+    // how many elements from the start of the list are already painted into the surface
+    painted: usize,
+}
+
+impl Backing {
+    pub fn new() -> Backing {
+        Backing {
+            surface: None,
+            scale: 1.0,
+            painted: 0,
+        }
+    }
+
+    /// drop the cached image, it is rebuilt from scratch on the next paint. Has to be called
+    /// whenever elements are removed or changed instead of only appended.
+    pub fn invalidate(&mut self) {
+        self.surface = None;
+        self.painted = 0;
+    }
+
+    /// paint the finished elements to `ctx`, rasterizing only the ones that were added since
+    /// the last call. `scale` is the one of the output, the target of a draw func is always
+    /// recorded at 1 and does not know it. `width` and `height` are in logical pixels.
+    pub fn paint(
+        &mut self,
+        ctx: &Context,
+        width: i32,
+        height: i32,
+        scale: f64,
+        finished: &[Box<dyn DrawingTool>],
+    ) -> Result<()> {
+        let pixel_width = (width as f64 * scale).ceil() as i32;
+        let pixel_height = (height as f64 * scale).ceil() as i32;
+
+        let outdated = match &self.surface {
+            Some(surface) => {
+                surface.width() != pixel_width
+                    || surface.height() != pixel_height
+                    || self.scale != scale
+                    || self.painted > finished.len()
+            }
+            None => true,
+        };
+        if outdated {
+            let surface = ImageSurface::create(Format::ARgb32, pixel_width, pixel_height)?;
+            surface.set_device_scale(scale, scale);
+            self.surface = Some(surface);
+            self.scale = scale;
+            self.painted = 0;
+        }
+
+        if let Some(surface) = &self.surface {
+            if self.painted < finished.len() {
+                let mut surface_ctx = Context::new(surface)?;
+                for element in &finished[self.painted..] {
+                    // one broken element should not take the rest of the drawing with it. Every
+                    // element starts from the same state, and since an error sticks to a cairo
+                    // context the next ones get a new context.
+                    if let Err(e) = saved(&surface_ctx, || element.draw(&surface_ctx)) {
+                        eprintln!("could not draw element: {e}");
+                        surface_ctx = Context::new(surface)?;
+                    }
+                }
+                self.painted = finished.len();
+            }
+            ctx.set_source_surface(surface, 0.0, 0.0)?;
+            ctx.paint()?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Backing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
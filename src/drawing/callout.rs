@@ -0,0 +1,412 @@
+use std::f64::consts::PI;
+
+use gtk::cairo::Context;
+use gtk::gdk::{Key, ModifierType, RGBA};
+use gtk::pango;
+
+use crate::colors::{self, Color};
+use crate::error::Result;
+
+use super::drawing_tool::{
+    drag_ends, draw_handle, draw_layout, near_handle, square, Bounds, DrawingTool, Point,
+    TextStyle, HANDLE_SIZE,
+};
+use super::fill::{Fill, FillMode};
+use super::line_style::LineStyle;
+
+// the space between the outline and the text
+const PADDING: f64 = 8.0;
+// a click without a drag places a bubble this big
+const DEFAULT_SIZE: Point = Point(220.0, 60.0);
+// a drag shorter than this in both directions counts as a click
+const MIN_DRAG: f64 = 8.0;
+// how far below the bubble the tail points at first
+const TAIL_LENGTH: f64 = 40.0;
+// how wide the tail is where it leaves the bubble, at most
+const TAIL_BASE: f64 = 24.0;
+
+/// A speech bubble: a rounded box with a tail pointing at something and text in it. The box is
+/// dragged out like a rectangle, then the text is typed and the tail is moved by dragging the
+/// handle at its tip. The text wraps at the width of the box and the box grows downwards to
+/// fit it. Shift+Return starts a new line, Return or a click outside the bubble finishes it.
+pub struct Callout {
+    // where the drag started, like the rectangle it can be the center
+    anchor: Option<Point>,
+    start: Option<Point>,
+    end: Option<Point>,
+    modifiers: ModifierType,
+    tip: Option<Point>,
+    text: String,
+    style: Option<TextStyle>,
+    // the first drag is going on
+    dragging: bool,
+    moving_tip: bool,
+    finished: bool,
+    line_width: f64,
+    line_style: LineStyle,
+    color: Color,
+    fill: Fill,
+    corner_radius: f64,
+}
+
+impl Callout {
+    pub fn new() -> Callout {
+        Callout {
+            anchor: None,
+            start: None,
+            end: None,
+            modifiers: ModifierType::empty(),
+            tip: None,
+            text: String::new(),
+            style: None,
+            dragging: false,
+            moving_tip: false,
+            finished: false,
+            line_width: 2.0,
+            line_style: LineStyle::Solid,
+            color: colors::RED,
+            fill: Fill::default(),
+            corner_radius: 12.0,
+        }
+    }
+
+    // Shift makes a square, Alt draws from the center
+    fn drag_to(&mut self, point: Point, modifiers: ModifierType) {
+        if let Some((start, end)) = drag_ends(self.anchor, point, modifiers, square) {
+            self.start = Some(start);
+            self.end = Some(end);
+            self.modifiers = modifiers;
+        }
+    }
+
+    // the box is placed once the first drag is over, then text can be typed into it
+    fn placed(&self) -> bool {
+        self.start.is_some() && !self.dragging
+    }
+
+    fn padding(&self) -> f64 {
+        self.line_style.width(self.line_width) / 2.0 + PADDING
+    }
+
+    // the text wrapped at `width`, `None` until the overlay gave the element its text style
+    fn layout(&self, width: f64) -> Option<pango::Layout> {
+        let style = self.style.as_ref()?;
+        let layout = pango::Layout::new(&style.context);
+        layout.set_font_description(Some(&style.font));
+        layout.set_wrap(pango::WrapMode::WordChar);
+        layout.set_width((width.max(1.0) * pango::SCALE as f64) as i32);
+        layout.set_text(&self.text);
+        Some(layout)
+    }
+
+    // the dragged box, made taller when the text does not fit into it
+    fn bubble(&self) -> Option<Bounds> {
+        let bounds = Bounds::from_points(&[self.start?, self.end?])?;
+        let padding = self.padding();
+        let text_height = self
+            .layout(bounds.width() - 2.0 * padding)
+            .map_or(0.0, |layout| layout.pixel_size().1 as f64);
+        let bottom = bounds.max.1.max(bounds.min.1 + text_height + 2.0 * padding);
+        Some(Bounds {
+            min: bounds.min,
+            max: Point(bounds.max.0, bottom),
+        })
+    }
+
+    // the side of the bubble the tail leaves from, counted clockwise from the top, and the two
+    // ends of its base in the order the outline passes them. `None` when the tip is inside.
+    fn tail(bubble: Bounds, radius: f64, tip: Point) -> Option<(usize, Point, Point)> {
+        let center = (bubble.min + bubble.max) / 2.0;
+        let half = (bubble.max - bubble.min) / 2.0;
+        let offset = tip - center;
+        if offset.0.abs() <= half.0 && offset.1.abs() <= half.1 {
+            return None;
+        }
+        let (min, max) = (bubble.min, bubble.max);
+        // the straight part of the side, in the direction the outline goes
+        let vertical = offset.1.abs() / half.1.max(1.0) >= offset.0.abs() / half.0.max(1.0);
+        let (side, from, to) = match vertical {
+            true if offset.1 < 0.0 => (
+                0,
+                Point(min.0 + radius, min.1),
+                Point(max.0 - radius, min.1),
+            ),
+            false if offset.0 > 0.0 => (
+                1,
+                Point(max.0, min.1 + radius),
+                Point(max.0, max.1 - radius),
+            ),
+            true => (
+                2,
+                Point(max.0 - radius, max.1),
+                Point(min.0 + radius, max.1),
+            ),
+            false => (
+                3,
+                Point(min.0, max.1 - radius),
+                Point(min.0, min.1 + radius),
+            ),
+        };
+        let edge = to - from;
+        let length = edge.0.hypot(edge.1);
+        let base = TAIL_BASE.min(length / 2.0);
+        if base < 1.0 {
+            return None;
+        }
+        let direction = edge / length;
+        // the tail leaves the side where the tip is, as far as the side reaches
+        let along = (tip - from).0 * direction.0 + (tip - from).1 * direction.1;
+        let middle = from + direction * along.clamp(base / 2.0, length - base / 2.0);
+        Some((
+            side,
+            middle - direction * (base / 2.0),
+            middle + direction * (base / 2.0),
+        ))
+    }
+
+    // the rounded box and the tail as one path, so the outline goes around both
+    fn outline(&self, cnx: &Context, bubble: Bounds) {
+        let radius = self
+            .corner_radius
+            .min(bubble.width() / 2.0)
+            .min(bubble.height() / 2.0)
+            .max(0.0);
+        let tail = self
+            .tip
+            .and_then(|tip| Some((Callout::tail(bubble, radius, tip)?, tip)));
+        let (min, max) = (
+            bubble.min + Point(radius, radius),
+            bubble.max - Point(radius, radius),
+        );
+        // the center of each corner and where its arc starts, each side leads to a corner
+        let corners = [
+            (Point(max.0, min.1), -PI / 2.0),
+            (max, 0.0),
+            (Point(min.0, max.1), PI / 2.0),
+            (min, PI),
+        ];
+        cnx.new_sub_path();
+        cnx.move_to(min.0, bubble.min.1);
+        for (side, (center, angle)) in corners.into_iter().enumerate() {
+            if let Some(((_, first, second), tip)) = tail.filter(|((at, ..), _)| *at == side) {
+                cnx.line_to(first.0, first.1);
+                cnx.line_to(tip.0, tip.1);
+                cnx.line_to(second.0, second.1);
+            }
+            cnx.arc(center.0, center.1, radius, angle, angle + PI / 2.0);
+        }
+        cnx.close_path();
+    }
+
+    // callouts are always filled so the text stays readable, white unless the fill has a
+    // color of its own
+    fn background(&self) -> Color {
+        let fill = Fill {
+            mode: match self.fill.mode {
+                FillMode::None => FillMode::Solid,
+                mode => mode,
+            },
+            color: Some(self.fill.color.unwrap_or(RGBA::WHITE)),
+            opacity: self.fill.opacity,
+        };
+        fill.color(self.color).unwrap_or(RGBA::WHITE)
+    }
+
+    fn near_tip(&self, point: Point) -> bool {
+        self.tip
+            .is_some_and(|tip| near_handle(tip, point, self.line_width))
+    }
+}
+
+impl Default for Callout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawingTool for Callout {
+    fn release_mouse(&mut self, point: Point) {
+        if self.moving_tip {
+            self.tip = Some(point);
+            self.moving_tip = false;
+            return;
+        }
+        if !self.dragging {
+            return;
+        }
+        self.drag_to(point, self.modifiers);
+        self.dragging = false;
+        let (Some(anchor), Some(start), Some(end)) = (self.anchor, self.start, self.end) else {
+            return;
+        };
+        let size = end - start;
+        if size.0.abs() < MIN_DRAG && size.1.abs() < MIN_DRAG {
+            self.start = Some(anchor);
+            self.end = Some(anchor + DEFAULT_SIZE);
+        }
+        if let Some(bubble) = self.bubble() {
+            self.tip = Some(Point(
+                bubble.min.0 + bubble.width() / 4.0,
+                bubble.max.1 + TAIL_LENGTH,
+            ));
+        }
+    }
+
+    fn press_mouse(&mut self, point: Point, modifiers: ModifierType) {
+        if self.start.is_none() {
+            self.anchor = Some(point);
+            self.start = Some(point);
+            self.end = Some(point);
+            self.modifiers = modifiers;
+            self.dragging = true;
+        } else if self.near_tip(point) {
+            // a press inside the bubble only keeps it editable
+            self.moving_tip = true;
+        }
+    }
+
+    fn motion_notify(&mut self, point: Point, modifiers: ModifierType) {
+        if self.finished {
+            return;
+        }
+        if self.dragging {
+            self.drag_to(point, modifiers);
+        } else if self.moving_tip {
+            self.tip = Some(point);
+        }
+    }
+
+    fn draw(&self, cnx: &Context) -> Result<()> {
+        let Some(bubble) = self.bubble() else {
+            return Ok(());
+        };
+        self.outline(cnx, bubble);
+        let background = self.background();
+        cnx.set_source_rgba(
+            background.red().into(),
+            background.green().into(),
+            background.blue().into(),
+            background.alpha().into(),
+        );
+        cnx.fill_preserve()?;
+
+        let color = self.color;
+        cnx.set_source_rgba(
+            color.red().into(),
+            color.green().into(),
+            color.blue().into(),
+            color.alpha().into(),
+        );
+        cnx.set_line_cap(gtk::cairo::LineCap::Round);
+        cnx.set_line_join(gtk::cairo::LineJoin::Round);
+        cnx.set_line_width(self.line_width);
+        self.line_style.stroke(cnx)?;
+
+        let padding = self.padding();
+        let Some(layout) = self.layout(bubble.width() - 2.0 * padding) else {
+            return Ok(());
+        };
+        let origin = bubble.min + Point(padding, padding);
+        draw_layout(cnx, &layout, color, origin)?;
+
+        // the caret and the handle only show while the bubble is edited
+        if !self.active() || !self.placed() {
+            return Ok(());
+        }
+        let (caret, _) = layout.cursor_pos(self.text.len() as i32);
+        let scale = pango::SCALE as f64;
+        let x = origin.0 + caret.x() as f64 / scale;
+        let y = origin.1 + caret.y() as f64 / scale;
+        cnx.move_to(x, y);
+        cnx.line_to(x, y + caret.height() as f64 / scale);
+        cnx.set_line_width(1.5);
+        cnx.stroke()?;
+
+        // the handle at the tip of the tail
+        if let Some(tip) = self.tip.filter(|_| !self.moving_tip) {
+            draw_handle(cnx, tip)?;
+        }
+        Ok(())
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        self.line_width = width;
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.line_style = style;
+    }
+
+    fn set_fill(&mut self, fill: Fill) {
+        self.fill = fill;
+    }
+
+    fn set_corner_radius(&mut self, radius: f64) {
+        self.corner_radius = radius;
+    }
+
+    fn set_text_style(&mut self, style: TextStyle) {
+        self.style = Some(style);
+    }
+
+    fn active(&self) -> bool {
+        self.start.is_some() && !self.finished
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let bubble = self.bubble()?;
+        let bounds = match self.tip {
+            Some(tip) => bubble.extend(tip),
+            None => bubble,
+        };
+        Some(bounds.grow(self.line_style.width(self.line_width) + HANDLE_SIZE + 1.0))
+    }
+
+    fn handles(&self, point: Point) -> bool {
+        if !self.placed() {
+            return false;
+        }
+        let inside = self.bubble().is_some_and(|bubble| bubble.contains(point));
+        inside || self.near_tip(point)
+    }
+
+    fn finish(&mut self) {
+        self.dragging = false;
+        self.moving_tip = false;
+        self.finished = true;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    // a click next to the bubble ends the typing, it would be a stray new callout otherwise
+    fn takes_finishing_click(&self) -> bool {
+        true
+    }
+
+    fn key_press(&mut self, key: Key, modifiers: ModifierType) -> bool {
+        if !self.placed()
+            || modifiers.intersects(ModifierType::CONTROL_MASK | ModifierType::ALT_MASK)
+        {
+            return false;
+        }
+        match key {
+            Key::BackSpace => {
+                self.text.pop();
+            }
+            Key::Return | Key::KP_Enter if modifiers.contains(ModifierType::SHIFT_MASK) => {
+                self.text.push('\n');
+            }
+            _ => match key.to_unicode().filter(|c| !c.is_control()) {
+                Some(c) => self.text.push(c),
+                None => return false,
+            },
+        }
+        true
+    }
+}
@@ -0,0 +1,230 @@
+use gtk::cairo::Context;
+use gtk::gdk::ModifierType;
+
+use crate::colors::{self, Color};
+use crate::error::Result;
+
+use super::arrow_head::ArrowHeads;
+use super::drawing_tool::{
+    drag_ends, draw_handle, draw_translucent, near_handle, snap_angle, Bounds, DrawingTool, Point,
+    HANDLE_SIZE,
+};
+use super::line_style::LineStyle;
+
+/// An arrow along a quadratic curve. It is dragged out like the straight arrow and then bent by
+/// dragging the handle in its middle, or by holding Ctrl during the first drag. The dragged
+/// point is the middle of the curve rather than its control point, so the curve follows the
+/// pointer.
+pub struct CurvedArrow {
+    // where the first drag started, like the straight arrow it can be the center
+    anchor: Option<Point>,
+    start: Option<Point>,
+    end: Option<Point>,
+    // the control point of the curve, `None` while it is straight
+    control: Option<Point>,
+    heads: ArrowHeads,
+    line_width: f64,
+    line_style: LineStyle,
+    // a button is held, during the first drag or while bending
+    dragging: bool,
+    bending: bool,
+    finished: bool,
+    color: Color,
+}
+
+impl CurvedArrow {
+    pub fn new() -> CurvedArrow {
+        CurvedArrow {
+            anchor: None,
+            start: None,
+            end: None,
+            control: None,
+            heads: ArrowHeads::default(),
+            line_width: 2.0,
+            line_style: LineStyle::Solid,
+            dragging: false,
+            bending: false,
+            finished: false,
+            color: colors::RED,
+        }
+    }
+
+    fn control(&self, start: Point, end: Point) -> Point {
+        self.control.unwrap_or((start + end) / 2.0)
+    }
+
+    // the point of the curve halfway between start and end, where the handle is
+    fn middle(&self) -> Option<Point> {
+        let (start, end) = (self.start?, self.end?);
+        Some((start + end) * 0.25 + self.control(start, end) * 0.5)
+    }
+
+    // move the control point so the middle of the curve is at `point`
+    fn bend(&mut self, point: Point) {
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            self.control = Some(point * 2.0 - (start + end) / 2.0);
+        }
+    }
+
+    // the curve and the heads, in the current source
+    fn draw_arrow(&self, cnx: &Context, start: Point, end: Point) -> Result<()> {
+        let control = self.control(start, end);
+        // the heads point along the tangents at the ends, which go through the control point
+        // unless it sits right on an end
+        let direction = |from: Point, to: Point| {
+            let tangent = to - from;
+            let length = tangent.0.hypot(tangent.1);
+            if length > f64::EPSILON {
+                tangent / length
+            } else {
+                let chord = end - start;
+                chord / chord.0.hypot(chord.1).max(f64::EPSILON)
+            }
+        };
+        let start_direction = direction(control, start);
+        let end_direction = direction(control, end);
+
+        // the curve stops short of heads that would get blunted by it
+        let curve_start =
+            start - start_direction * self.heads.inset(self.heads.start, self.line_width);
+        let curve_end = end - end_direction * self.heads.inset(self.heads.end, self.line_width);
+        // the quadratic curve as the cubic one cairo draws
+        let first = curve_start + (control - curve_start) * (2.0 / 3.0);
+        let second = curve_end + (control - curve_end) * (2.0 / 3.0);
+        cnx.move_to(curve_start.0, curve_start.1);
+        cnx.curve_to(
+            first.0,
+            first.1,
+            second.0,
+            second.1,
+            curve_end.0,
+            curve_end.1,
+        );
+        self.line_style.stroke(cnx)?;
+
+        let angle = |direction: Point| direction.1.atan2(direction.0);
+        self.heads.draw(
+            cnx,
+            self.heads.start,
+            start,
+            angle(start_direction),
+            self.line_width,
+        )?;
+        self.heads.draw(
+            cnx,
+            self.heads.end,
+            end,
+            angle(end_direction),
+            self.line_width,
+        )?;
+        Ok(())
+    }
+}
+
+impl Default for CurvedArrow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawingTool for CurvedArrow {
+    fn release_mouse(&mut self, point: Point) {
+        self.dragging = false;
+        if self.bending {
+            self.bend(point);
+            self.finished = true;
+        } else if let (Some(start), Some(end)) = (self.start, self.end) {
+            // a click without a drag, there is nothing to bend
+            let length = end - start;
+            if length.0.hypot(length.1) < 1.0 {
+                self.finished = true;
+            }
+        }
+    }
+
+    fn press_mouse(&mut self, point: Point, _: ModifierType) {
+        if self.start.is_none() {
+            self.anchor = Some(point);
+            self.start = Some(point);
+            self.end = Some(point);
+        } else {
+            // a second drag, `handles` made sure it is on the handle
+            self.bending = true;
+            self.bend(point);
+        }
+        self.dragging = true;
+    }
+
+    fn motion_notify(&mut self, point: Point, modifiers: ModifierType) {
+        if !self.dragging || self.finished {
+            return;
+        }
+        if self.bending || modifiers.contains(ModifierType::CONTROL_MASK) {
+            self.bend(point);
+        } else if let Some((start, end)) = drag_ends(self.anchor, point, modifiers, snap_angle) {
+            // the ends are dragged like the ones of the straight arrow
+            self.start = Some(start);
+            self.end = Some(end);
+        }
+    }
+
+    fn draw(&self, cnx: &Context) -> Result<()> {
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            draw_translucent(cnx, self.bounds(), self.color, || {
+                cnx.set_line_cap(gtk::cairo::LineCap::Round);
+                cnx.set_line_join(gtk::cairo::LineJoin::Round);
+                cnx.set_line_width(self.line_width);
+                self.draw_arrow(cnx, start, end)
+            })?;
+        }
+
+        // the handle only shows while the curve can still be bent
+        if let Some(middle) = self.middle().filter(|_| self.active() && !self.dragging) {
+            draw_handle(cnx, middle)?;
+        }
+        Ok(())
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        self.line_width = width;
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.line_style = style;
+    }
+
+    fn set_arrow_heads(&mut self, heads: ArrowHeads) {
+        self.heads = heads;
+    }
+
+    fn active(&self) -> bool {
+        self.start.is_some() && !self.finished
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let (start, end) = (self.start?, self.end?);
+        // the curve stays inside the triangle of its ends and the control point
+        let control = self.control(start, end);
+        let head = self.heads.size(self.line_width);
+        Some(
+            Bounds::from_points(&[start, end, control])?
+                .grow(head + self.line_style.width(self.line_width) + HANDLE_SIZE),
+        )
+    }
+
+    fn handles(&self, point: Point) -> bool {
+        match self.middle() {
+            Some(middle) if !self.dragging => near_handle(middle, point, self.line_width),
+            _ => false,
+        }
+    }
+
+    fn finish(&mut self) {
+        self.dragging = false;
+        self.finished = true;
+    }
+}
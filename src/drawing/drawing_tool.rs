@@ -1,6 +1,17 @@
+use std::f64::consts::PI;
+
 use gtk::cairo::Context;
+use gtk::gdk::{Key, ModifierType};
+use gtk::pango;
+use gtk::prelude::*;
 
+use super::arrow_head::ArrowHeads;
+use super::fill::Fill;
+use super::line_style::LineStyle;
+use super::stamp::StampSource;
+use super::step_badge::StepStyle;
 use crate::colors;
+use crate::error::Result;
 
 #[derive(Clone, Debug, Copy)]
 pub struct Point(pub f64, pub f64);
@@ -43,20 +54,294 @@ impl std::ops::Neg for Point {
     }
 }
 
-#[derive(Debug)]
+/// Axis aligned box around everything an element paints, used to limit redraws to the area
+/// that actually changed.
+#[derive(Clone, Debug, Copy)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn from_points(points: &[Point]) -> Option<Bounds> {
+        let first = *points.first()?;
+        Some(points.iter().fold(
+            Bounds {
+                min: first,
+                max: first,
+            },
+            |bounds, point| bounds.extend(*point),
+        ))
+    }
+
+    pub fn extend(self, point: Point) -> Bounds {
+        Bounds {
+            min: Point(self.min.0.min(point.0), self.min.1.min(point.1)),
+            max: Point(self.max.0.max(point.0), self.max.1.max(point.1)),
+        }
+    }
+
+    pub fn union(self, other: Bounds) -> Bounds {
+        self.extend(other.min).extend(other.max)
+    }
+
+    /// grow the box on every side, e.g. by half the line width so the stroke is included
+    pub fn grow(self, by: f64) -> Bounds {
+        Bounds {
+            min: self.min - Point(by, by),
+            max: self.max + Point(by, by),
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min.0..=self.max.0).contains(&point.0) && (self.min.1..=self.max.1).contains(&point.1)
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max.0 - self.min.0
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max.1 - self.min.1
+    }
+}
+
+// Shift snaps lines to multiples of this angle
+const SNAP_ANGLE: f64 = std::f64::consts::PI / 12.0;
+
+/// `point` moved onto the closest line from `anchor` at a multiple of 15°
+pub fn snap_angle(anchor: Point, point: Point) -> Point {
+    let offset = point - anchor;
+    let length = offset.0.hypot(offset.1);
+    let angle = (offset.1.atan2(offset.0) / SNAP_ANGLE).round() * SNAP_ANGLE;
+    anchor + Point(angle.cos(), angle.sin()) * length
+}
+
+/// `point` moved so the box between it and `anchor` is a square, as big as the longer side
+pub fn square(anchor: Point, point: Point) -> Point {
+    let offset = point - anchor;
+    let side = offset.0.abs().max(offset.1.abs());
+    anchor + Point(side.copysign(offset.0), side.copysign(offset.1))
+}
+
+/// the two ends of a shape dragged from `anchor` to `point`, nothing before the press set an
+/// anchor. Shift moves `point` with `constrain`, e.g. `snap_angle` or `square`, and with Alt
+/// the anchor is the center of the shape instead of one of its ends.
+pub fn drag_ends(
+    anchor: Option<Point>,
+    point: Point,
+    modifiers: ModifierType,
+    constrain: fn(Point, Point) -> Point,
+) -> Option<(Point, Point)> {
+    let anchor = anchor?;
+    let point = match modifiers.contains(ModifierType::SHIFT_MASK) {
+        true => constrain(anchor, point),
+        false => point,
+    };
+    if modifiers.contains(ModifierType::ALT_MASK) {
+        Some((anchor * 2.0 - point, point))
+    } else {
+        Some((anchor, point))
+    }
+}
+
+/// the radius of the handles of elements that are still edited, e.g. to bend a curved arrow
+pub const HANDLE_SIZE: f64 = 5.0;
+// how close to a handle a press has to be to grab it
+const HANDLE_GRAB_DISTANCE: f64 = 12.0;
+
+/// a handle at `at`, white with a dark outline so it shows on any color
+pub fn draw_handle(cnx: &Context, at: Point) -> Result<()> {
+    cnx.arc(at.0, at.1, HANDLE_SIZE, 0.0, 2.0 * PI);
+    cnx.set_source_rgb(1.0, 1.0, 1.0);
+    cnx.fill_preserve()?;
+    cnx.set_source_rgba(0.0, 0.0, 0.0, 0.6);
+    cnx.set_line_width(1.5);
+    cnx.stroke()?;
+    Ok(())
+}
+
+/// whether a press at `point` grabs the handle at `handle`, on thick lines anywhere on the line
+pub fn near_handle(handle: Point, point: Point, line_width: f64) -> bool {
+    let distance = point - handle;
+    distance.0.hypot(distance.1) <= HANDLE_GRAB_DISTANCE.max(line_width)
+}
+
+/// Run `draw` between `save` and `restore`, the state is restored even when drawing fails.
+pub fn saved(cnx: &Context, draw: impl FnOnce() -> Result<()>) -> Result<()> {
+    cnx.save()?;
+    let drawn = draw();
+    let restored = cnx.restore();
+    drawn?;
+    restored?;
+    Ok(())
+}
+
+/// Draw with `draw` in the opaque `color` and blend the result with its opacity, for elements
+/// whose parts overlap and would otherwise get darker where they meet. The group is limited
+/// to `bounds`.
+pub fn draw_translucent(
+    cnx: &Context,
+    bounds: Option<Bounds>,
+    color: colors::Color,
+    draw: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    saved(cnx, || {
+        if let Some(bounds) = bounds {
+            cnx.rectangle(bounds.min.0, bounds.min.1, bounds.width(), bounds.height());
+            cnx.clip();
+        }
+        cnx.push_group();
+        cnx.set_source_rgb(
+            color.red().into(),
+            color.green().into(),
+            color.blue().into(),
+        );
+        let drawn = draw();
+        cnx.pop_group_to_source()?;
+        drawn?;
+        cnx.paint_with_alpha(color.alpha().into())?;
+        Ok(())
+    })
+}
+
+/// A rectangle path from `bounds.min` to `bounds.max` with corners rounded by `radius`, which
+/// is limited to half the shorter side.
+pub fn rounded_rectangle(cnx: &Context, bounds: Bounds, radius: f64) {
+    let radius = radius.min(bounds.width() / 2.0).min(bounds.height() / 2.0);
+    if radius <= 0.0 {
+        cnx.rectangle(bounds.min.0, bounds.min.1, bounds.width(), bounds.height());
+        return;
+    }
+    let (min, max) = (
+        bounds.min + Point(radius, radius),
+        bounds.max - Point(radius, radius),
+    );
+    cnx.new_sub_path();
+    cnx.arc(max.0, min.1, radius, -PI / 2.0, 0.0);
+    cnx.arc(max.0, max.1, radius, 0.0, PI / 2.0);
+    cnx.arc(min.0, max.1, radius, PI / 2.0, PI);
+    cnx.arc(min.0, min.1, radius, PI, 1.5 * PI);
+    cnx.close_path();
+}
+
+/// What elements with text lay it out with, the context comes from the overlay so the text
+/// matches the resolution and font options of the screen.
+#[derive(Debug, Clone)]
+pub struct TextStyle {
+    pub context: pango::Context,
+    pub font: pango::FontDescription,
+}
+
+/// Draw `layout` in `color` with its top left corner at `origin`.
+pub fn draw_layout(
+    cnx: &Context,
+    layout: &pango::Layout,
+    color: colors::Color,
+    origin: Point,
+) -> Result<()> {
+    // there is no pangocairo for this gtk, the layout goes through a render node instead
+    let snapshot = gtk::Snapshot::new();
+    snapshot.append_layout(layout, &color);
+    let Some(node) = snapshot.to_node() else {
+        return Ok(());
+    };
+    saved(cnx, || {
+        cnx.translate(origin.0, origin.1);
+        node.draw(cnx);
+        // the node does not report its errors, they are left in the context
+        cnx.status()?;
+        Ok(())
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CurrentDrawingTool {
     NormalLine,
     NormalArrowHeadBase,
     NormalArrowHeadPointer,
+    CurvedArrow,
     NormalRectangle,
+    Polyline,
+    Callout,
+    StepBadge,
+    Stamp,
+}
+
+impl CurrentDrawingTool {
+    /// a new, empty element drawn with this tool
+    pub fn create(&self) -> Box<dyn DrawingTool> {
+        match self {
+            CurrentDrawingTool::NormalLine => Box::new(super::normal_line::NormalLine::new()),
+            CurrentDrawingTool::NormalArrowHeadBase => {
+                Box::new(super::arrow::NormalArrow::new(true))
+            }
+            CurrentDrawingTool::NormalArrowHeadPointer => {
+                Box::new(super::arrow::NormalArrow::new(false))
+            }
+            CurrentDrawingTool::CurvedArrow => Box::new(super::curved_arrow::CurvedArrow::new()),
+            CurrentDrawingTool::NormalRectangle => {
+                Box::new(super::normal_rectangle::NormalRectangle::new())
+            }
+            CurrentDrawingTool::Polyline => Box::new(super::polyline::Polyline::new()),
+            CurrentDrawingTool::Callout => Box::new(super::callout::Callout::new()),
+            CurrentDrawingTool::StepBadge => Box::new(super::step_badge::StepBadge::new()),
+            CurrentDrawingTool::Stamp => Box::new(super::stamp::Stamp::new()),
+        }
+    }
 }
 
 pub trait DrawingTool {
     fn release_mouse(&mut self, point: Point);
-    fn press_mouse(&mut self, point: Point);
-    fn motion_notify(&mut self, point: Point);
-    fn draw(&self, cnx: &Context);
+    /// `modifiers` are the keys held during the event, tools use them to constrain or alter
+    /// the shape
+    fn press_mouse(&mut self, point: Point, modifiers: ModifierType);
+    fn motion_notify(&mut self, point: Point, modifiers: ModifierType);
+    fn draw(&self, cnx: &Context) -> Result<()>;
     fn set_line_width(&mut self, width: f64);
     fn set_color(&mut self, color: colors::Color);
-    fn active(&mut self) -> bool;
+    /// only tools with lines have a line style
+    fn set_line_style(&mut self, _style: LineStyle) {}
+    /// only arrows have heads
+    fn set_arrow_heads(&mut self, _heads: ArrowHeads) {}
+    /// only closed shapes are filled
+    fn set_fill(&mut self, _fill: Fill) {}
+    /// only boxes have corners to round
+    fn set_corner_radius(&mut self, _radius: f64) {}
+    /// only elements with text need it
+    fn set_text_style(&mut self, _style: TextStyle) {}
+    /// only step badges count, `series` is the one they are numbered in
+    fn set_step(&mut self, _series: usize, _style: StepStyle) {}
+    fn step_series(&self) -> Option<usize> {
+        None
+    }
+    fn set_step_number(&mut self, _number: usize) {}
+    /// only stamps show an image or a symbol
+    fn set_stamp(&mut self, _source: StampSource) {}
+    fn active(&self) -> bool;
+    /// the area the element covers, `None` while there is nothing to draw yet
+    fn bounds(&self) -> Option<Bounds>;
+    /// whether a press at `point` continues this element instead of starting a new one, only
+    /// asked while the element is active and no button is held
+    fn handles(&self, _point: Point) -> bool {
+        false
+    }
+    /// stop editing an element that stays active after the mouse is released
+    fn finish(&mut self) {}
+    /// whether the finished element has nothing worth keeping, it is removed then
+    fn is_empty(&self) -> bool {
+        false
+    }
+    /// whether a press outside the active element only finishes it instead of also starting a
+    /// new one
+    fn takes_finishing_click(&self) -> bool {
+        false
+    }
+    /// take back the last point placed by a click, for elements built click by click
+    fn remove_last_point(&mut self) {}
+    /// a key pressed while the element is active, elements with text take the keys they type
+    /// and leave the rest to the keybindings
+    fn key_press(&mut self, _key: Key, _modifiers: ModifierType) -> bool {
+        false
+    }
 }
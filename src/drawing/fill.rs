@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::colors::Color;
+
+/// how transparent a translucent fill is unless the config says otherwise
+pub const DEFAULT_TRANSLUCENT_OPACITY: f64 = 0.3;
+
+/// How closed shapes are filled, `fill.cycle` goes through them in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillMode {
+    None,
+    Solid,
+    Translucent,
+}
+
+impl FillMode {
+    pub fn next(self) -> FillMode {
+        match self {
+            FillMode::None => FillMode::Solid,
+            FillMode::Solid => FillMode::Translucent,
+            FillMode::Translucent => FillMode::None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FillMode::None => "none",
+            FillMode::Solid => "solid",
+            FillMode::Translucent => "translucent",
+        }
+    }
+}
+
+/// The fill of closed shapes like rectangles and polygons, the outline is stroked on top.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fill {
+    pub mode: FillMode,
+    /// `None` fills with the stroke color
+    pub color: Option<Color>,
+    /// used for the translucent mode
+    pub opacity: f64,
+}
+
+impl Default for Fill {
+    fn default() -> Self {
+        Fill {
+            mode: FillMode::None,
+            color: None,
+            opacity: DEFAULT_TRANSLUCENT_OPACITY,
+        }
+    }
+}
+
+impl Fill {
+    /// what to fill a shape stroked with `stroke` with, `None` when it is not filled
+    pub fn color(&self, stroke: Color) -> Option<Color> {
+        let mut color = self.color.unwrap_or(stroke);
+        match self.mode {
+            FillMode::None => return None,
+            FillMode::Solid => (),
+            FillMode::Translucent => color.set_alpha(color.alpha() * self.opacity as f32),
+        }
+        Some(color)
+    }
+
+    /// fill the current path if the shape is filled, the path is kept for the stroke
+    pub fn fill_preserve(
+        &self,
+        cnx: &gtk::cairo::Context,
+        stroke: Color,
+    ) -> crate::error::Result<()> {
+        if let Some(color) = self.color(stroke) {
+            cnx.set_source_rgba(
+                color.red().into(),
+                color.green().into(),
+                color.blue().into(),
+                color.alpha().into(),
+            );
+            cnx.fill_preserve()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use gtk::cairo::{Context, Operator};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+use super::drawing_tool::saved;
+
+/// How the lines of an element are stroked, `line_style.cycle` goes through them in this order.
+/// The patterns scale with the line width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+    DashDot,
+    /// two parallel lines, each as wide as the line width and as far apart
+    Double,
+}
+
+impl LineStyle {
+    pub fn next(self) -> LineStyle {
+        match self {
+            LineStyle::Solid => LineStyle::Dashed,
+            LineStyle::Dashed => LineStyle::Dotted,
+            LineStyle::Dotted => LineStyle::DashDot,
+            LineStyle::DashDot => LineStyle::Double,
+            LineStyle::Double => LineStyle::Solid,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineStyle::Solid => "solid",
+            LineStyle::Dashed => "dashed",
+            LineStyle::Dotted => "dotted",
+            LineStyle::DashDot => "dash-dot",
+            LineStyle::Double => "double",
+        }
+    }
+
+    /// how wide the stroke of a line of `line_width` really is
+    pub fn width(self, line_width: f64) -> f64 {
+        match self {
+            LineStyle::Double => line_width * 3.0,
+            _ => line_width,
+        }
+    }
+
+    /// stroke the current path in the current source and line width, afterwards the dash is
+    /// reset so e.g. arrow heads stay solid. The patterns expect round line caps.
+    pub fn stroke(self, cnx: &Context) -> Result<()> {
+        let width = cnx.line_width();
+        // round caps grow every dash by half the width on both ends, a dash of 0 is a dot
+        let dashes = match self {
+            LineStyle::Solid | LineStyle::Double => Vec::new(),
+            LineStyle::Dashed => vec![3.0 * width, 3.0 * width],
+            LineStyle::Dotted => vec![0.0, 2.0 * width],
+            LineStyle::DashDot => vec![3.0 * width, 2.5 * width, 0.0, 2.5 * width],
+        };
+
+        if self == LineStyle::Double {
+            // a wide line with its middle cut out, in a group so the cut does not reach what
+            // is already drawn below. The group only covers the line.
+            return saved(cnx, || {
+                cnx.set_line_width(width * 3.0);
+                let (x1, y1, x2, y2) = cnx.stroke_extents()?;
+                let path = cnx.copy_path()?;
+                cnx.new_path();
+                cnx.rectangle(
+                    x1.floor(),
+                    y1.floor(),
+                    x2.ceil() - x1.floor(),
+                    y2.ceil() - y1.floor(),
+                );
+                cnx.clip();
+                cnx.append_path(&path);
+                cnx.push_group();
+                cnx.stroke_preserve()?;
+                cnx.set_operator(Operator::Clear);
+                cnx.set_line_width(width);
+                cnx.stroke()?;
+                cnx.pop_group_to_source()?;
+                cnx.paint()?;
+                Ok(())
+            });
+        }
+
+        cnx.set_dash(&dashes, 0.0);
+        let stroked = cnx.stroke();
+        cnx.set_dash(&[], 0.0);
+        stroked?;
+        Ok(())
+    }
+}
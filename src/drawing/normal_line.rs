@@ -1,14 +1,22 @@
 use gtk::cairo::Context;
+use gtk::gdk::ModifierType;
 
 use crate::colors;
+use crate::error::Result;
 
-use super::drawing_tool::{DrawingTool, Point};
+use super::drawing_tool::{Bounds, DrawingTool, Point};
+use super::line_style::LineStyle;
 
 pub struct NormalLine {
     points: Vec<Point>,
+    // the points that start a straight segment to the next point, drawn with Shift held
+    straight: Vec<usize>,
+    // where the segment that is drawn straight right now starts
+    shift_from: Option<usize>,
     finished: bool,
     started: bool,
     line_width: f64,
+    line_style: LineStyle,
     color: colors::Color,
 }
 
@@ -16,12 +24,49 @@ impl NormalLine {
     pub fn new() -> NormalLine {
         NormalLine {
             points: Vec::new(),
+            straight: Vec::new(),
+            shift_from: None,
             finished: false,
             started: false,
             line_width: 2.0,
+            line_style: LineStyle::Solid,
             color: colors::RED,
         }
     }
+
+    // the freehand parts of the line, each one is joined to the next by a straight segment
+    fn runs(&self) -> Vec<&[Point]> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        for &from in &self.straight {
+            runs.push(&self.points[start..=from]);
+            start = from + 1;
+        }
+        runs.push(&self.points[start.min(self.points.len())..]);
+        runs
+    }
+}
+
+// a freehand part of the line from the current point, smoothed once it has enough points
+fn freehand(ctx: &Context, points: &[Point]) {
+    if points.len() > 3 {
+        let controls = calc_whole_spline(&points.to_vec());
+        for i in 0..points.len() - 2 {
+            let p_0 = points[i];
+            let p_1 = points[i + 1];
+            ctx.curve_to(
+                p_0.0 + controls[i].0,
+                p_0.1 + controls[i].1,
+                p_1.0 - controls[i + 1].0,
+                p_1.1 - controls[i + 1].1,
+                p_1.0,
+                p_1.1,
+            )
+        }
+    }
+    if let Some(last) = points.last().filter(|_| points.len() > 1) {
+        ctx.line_to(last.0, last.1);
+    }
 }
 // https://www.ibiblio.org/e-notes/Splines/b-int.html
 pub fn calc_whole_spline(points: &Vec<Point>) -> Vec<Point> {
@@ -55,48 +100,63 @@ impl DrawingTool for NormalLine {
         self.finished = true;
     }
 
-    fn press_mouse(&mut self, _: Point) {
+    fn press_mouse(&mut self, point: Point, _: ModifierType) {
         self.started = true;
+        self.points.push(point);
     }
 
-    fn motion_notify(&mut self, point: Point) {
-        if self.active() {
-            self.points.push(point);
+    fn motion_notify(&mut self, point: Point, modifiers: ModifierType) {
+        if !self.active() {
+            return;
         }
+        // with Shift the line goes straight from where Shift was pressed to the pointer, letting
+        // go continues freehand from there
+        if modifiers.contains(ModifierType::SHIFT_MASK) {
+            let from = match self.shift_from {
+                Some(from) => from,
+                None => {
+                    let from = self.points.len() - 1;
+                    self.straight.push(from);
+                    self.shift_from = Some(from);
+                    from
+                }
+            };
+            self.points.truncate(from + 1);
+        } else {
+            self.shift_from = None;
+        }
+        self.points.push(point);
     }
 
-    fn draw(&self, ctx: &Context) -> () {
+    fn draw(&self, ctx: &Context) -> Result<()> {
         let color = self.color;
-        ctx.set_source_rgb(
+        ctx.set_source_rgba(
             color.red().into(),
             color.green().into(),
             color.blue().into(),
+            color.alpha().into(),
         );
         ctx.set_line_width(self.line_width);
         ctx.set_line_cap(gtk::cairo::LineCap::Round);
         ctx.set_line_join(gtk::cairo::LineJoin::Round);
 
-        if self.points.len() > 3 {
-            let controls = calc_whole_spline(&self.points);
-            let first_point = self.points[0];
-            ctx.move_to(first_point.0, first_point.1);
-            for i in 0..self.points.len() - 2 {
-                let p_0 = self.points[i];
-                let p_1 = self.points[i + 1];
-                ctx.curve_to(
-                    p_0.0 + controls[i].0,
-                    p_0.1 + controls[i].1,
-                    p_1.0 - controls[i + 1].0,
-                    p_1.1 - controls[i + 1].1,
-                    p_1.0,
-                    p_1.1,
-                )
-            }
-            match ctx.stroke() {
-                Err(e) => panic!("{e}"),
-                _ => (),
+        if self.points.len() < 2 {
+            return Ok(());
+        }
+        for (index, run) in self.runs().into_iter().enumerate() {
+            let Some(first) = run.first() else {
+                continue;
+            };
+            // the first run starts the line, the others are reached by a straight segment
+            if index == 0 {
+                ctx.move_to(first.0, first.1);
+            } else {
+                ctx.line_to(first.0, first.1);
             }
+            freehand(ctx, run);
         }
+        self.line_style.stroke(ctx)?;
+        Ok(())
     }
 
     fn set_line_width(&mut self, width: f64) {
@@ -107,7 +167,26 @@ impl DrawingTool for NormalLine {
         self.color = color;
     }
 
-    fn active(&mut self) -> bool {
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.line_style = style;
+    }
+
+    fn active(&self) -> bool {
         return self.started && !self.finished;
     }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let bounds = Bounds::from_points(&self.points)?;
+        // the curves stay inside the hull of their control points
+        let bounds = self.runs().into_iter().fold(bounds, |bounds, run| {
+            let controls = calc_whole_spline(&run.to_vec());
+            controls
+                .iter()
+                .zip(run.iter())
+                .fold(bounds, |bounds, (control, point)| {
+                    bounds.extend(*point + *control).extend(*point - *control)
+                })
+        });
+        Some(bounds.grow(self.line_style.width(self.line_width) / 2.0 + 1.0))
+    }
 }
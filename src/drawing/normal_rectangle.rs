@@ -1,67 +1,91 @@
+use gtk::gdk::ModifierType;
+
 use crate::colors;
+use crate::error::Result;
 
-use super::drawing_tool::{DrawingTool, Point};
+use super::drawing_tool::{drag_ends, rounded_rectangle, square, Bounds, DrawingTool, Point};
+use super::fill::Fill;
+use super::line_style::LineStyle;
 
 pub struct NormalRectangle {
+    // where the drag started, a corner unless the rectangle is drawn from its center
+    anchor: Option<Point>,
+    // held during the last motion, the release event does not carry them
+    modifiers: ModifierType,
     start: Option<Point>,
     end: Option<Point>,
     finished: bool,
     line_width: f64,
+    line_style: LineStyle,
     color: colors::Color,
+    fill: Fill,
+    corner_radius: f64,
 }
 
 impl NormalRectangle {
     pub fn new() -> NormalRectangle {
         NormalRectangle {
+            anchor: None,
+            modifiers: ModifierType::empty(),
             start: None,
             end: None,
             finished: false,
             line_width: 2.0,
+            line_style: LineStyle::Solid,
             color: colors::RED,
+            fill: Fill::default(),
+            corner_radius: 0.0,
+        }
+    }
+
+    // Shift makes a square, Alt draws from the center
+    fn drag_to(&mut self, point: Point, modifiers: ModifierType) {
+        if let Some((start, end)) = drag_ends(self.anchor, point, modifiers, square) {
+            self.start = Some(start);
+            self.end = Some(end);
+            self.modifiers = modifiers;
         }
     }
 }
 
 impl DrawingTool for NormalRectangle {
     fn release_mouse(&mut self, point: Point) {
-        self.end = Some(point);
+        self.drag_to(point, self.modifiers);
         self.finished = true;
     }
 
-    fn press_mouse(&mut self, point: Point) {
+    fn press_mouse(&mut self, point: Point, modifiers: ModifierType) {
+        self.anchor = Some(point);
         self.start = Some(point);
+        self.modifiers = modifiers;
     }
 
-    fn motion_notify(&mut self, point: Point) {
+    fn motion_notify(&mut self, point: Point, modifiers: ModifierType) {
         if !self.finished {
-            self.end = Some(point);
+            self.drag_to(point, modifiers);
         }
     }
 
-    fn draw(&self, cnx: &gtk::cairo::Context) {
+    fn draw(&self, cnx: &gtk::cairo::Context) -> Result<()> {
         if let (Some(start), Some(end)) = (self.start, self.end) {
+            if let Some(bounds) = Bounds::from_points(&[start, end]) {
+                rounded_rectangle(cnx, bounds, self.corner_radius);
+            }
+            self.fill.fill_preserve(cnx, self.color)?;
+
             let color = self.color;
-            cnx.set_source_rgb(
+            cnx.set_source_rgba(
                 color.red().into(),
                 color.green().into(),
                 color.blue().into(),
+                color.alpha().into(),
             );
             cnx.set_line_cap(gtk::cairo::LineCap::Round);
             cnx.set_line_join(gtk::cairo::LineJoin::Round);
             cnx.set_line_width(self.line_width);
-
-            cnx.rectangle(
-                f64::min(start.0, end.0),
-                f64::min(start.1, end.1),
-                (end.0 - start.0).abs(),
-                (end.1 - start.1).abs(),
-            );
-        }
-
-        match cnx.stroke() {
-            Err(e) => println!("{e}"),
-            _ => (),
+            self.line_style.stroke(cnx)?;
         }
+        Ok(())
     }
 
     fn set_line_width(&mut self, width: f64) {
@@ -72,7 +96,27 @@ impl DrawingTool for NormalRectangle {
         self.color = color
     }
 
-    fn active(&mut self) -> bool {
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.line_style = style;
+    }
+
+    fn set_fill(&mut self, fill: Fill) {
+        self.fill = fill;
+    }
+
+    fn set_corner_radius(&mut self, radius: f64) {
+        self.corner_radius = radius;
+    }
+
+    fn active(&self) -> bool {
         return self.start.is_some() && !self.finished;
     }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let (start, end) = (self.start?, self.end?);
+        Some(
+            Bounds::from_points(&[start, end])?
+                .grow(self.line_style.width(self.line_width) / 2.0 + 1.0),
+        )
+    }
 }
@@ -0,0 +1,187 @@
+use std::f64::consts::PI;
+
+use gtk::cairo::Context;
+use gtk::gdk::ModifierType;
+
+use crate::colors::{self, Color};
+use crate::error::Result;
+
+use super::drawing_tool::{snap_angle, Bounds, DrawingTool, Point};
+use super::fill::Fill;
+use super::line_style::LineStyle;
+
+// how close to the first vertex a click has to be to close the polygon
+const CLOSE_DISTANCE: f64 = 10.0;
+
+/// Straight segments placed click by click. The segment to the pointer follows it until the
+/// line is finished with a double click or Enter, a click on the first vertex closes it into a
+/// polygon.
+pub struct Polyline {
+    vertices: Vec<Point>,
+    // the end of the segment that follows the pointer
+    pointer: Option<Point>,
+    closed: bool,
+    finished: bool,
+    line_width: f64,
+    line_style: LineStyle,
+    color: Color,
+    fill: Fill,
+}
+
+impl Polyline {
+    pub fn new() -> Polyline {
+        Polyline {
+            vertices: Vec::new(),
+            pointer: None,
+            closed: false,
+            finished: false,
+            line_width: 2.0,
+            line_style: LineStyle::Solid,
+            color: colors::RED,
+            fill: Fill::default(),
+        }
+    }
+
+    // a click there closes the polygon
+    fn near_first(&self, point: Point) -> bool {
+        match self.vertices.first() {
+            Some(first) if self.vertices.len() > 2 => {
+                let distance = point - *first;
+                distance.0.hypot(distance.1) <= CLOSE_DISTANCE.max(self.line_width)
+            }
+            _ => false,
+        }
+    }
+
+    // Shift snaps the segment from the last vertex like an arrow
+    fn constrain(&self, point: Point, modifiers: ModifierType) -> Point {
+        match self.vertices.last() {
+            Some(last) if modifiers.contains(ModifierType::SHIFT_MASK) => snap_angle(*last, point),
+            _ => point,
+        }
+    }
+}
+
+impl Default for Polyline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawingTool for Polyline {
+    // vertices are placed on press, the release does not end the line
+    fn release_mouse(&mut self, _: Point) {}
+
+    fn press_mouse(&mut self, point: Point, modifiers: ModifierType) {
+        if self.near_first(point) {
+            self.closed = true;
+            self.finish();
+            return;
+        }
+        let point = self.constrain(point, modifiers);
+        self.vertices.push(point);
+        self.pointer = Some(point);
+    }
+
+    fn motion_notify(&mut self, point: Point, modifiers: ModifierType) {
+        if self.active() {
+            self.pointer = Some(self.constrain(point, modifiers));
+        }
+    }
+
+    fn draw(&self, cnx: &Context) -> Result<()> {
+        let Some(first) = self.vertices.first() else {
+            return Ok(());
+        };
+        cnx.move_to(first.0, first.1);
+        for vertex in &self.vertices[1..] {
+            cnx.line_to(vertex.0, vertex.1);
+        }
+        if self.closed {
+            cnx.close_path();
+            // only a polygon has an inside
+            self.fill.fill_preserve(cnx, self.color)?;
+        } else if let Some(pointer) = self.pointer.filter(|_| self.active()) {
+            cnx.line_to(pointer.0, pointer.1);
+        }
+
+        let color = self.color;
+        cnx.set_source_rgba(
+            color.red().into(),
+            color.green().into(),
+            color.blue().into(),
+            color.alpha().into(),
+        );
+        cnx.set_line_cap(gtk::cairo::LineCap::Round);
+        cnx.set_line_join(gtk::cairo::LineJoin::Round);
+        cnx.set_line_width(self.line_width);
+        self.line_style.stroke(cnx)?;
+
+        // a ring around the first vertex when a click would close the polygon
+        if self
+            .pointer
+            .is_some_and(|pointer| self.active() && self.near_first(pointer))
+        {
+            cnx.arc(
+                first.0,
+                first.1,
+                CLOSE_DISTANCE.max(self.line_width),
+                0.0,
+                2.0 * PI,
+            );
+            cnx.set_line_width(1.5);
+            cnx.stroke()?;
+        }
+        Ok(())
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        self.line_width = width;
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.line_style = style;
+    }
+
+    fn set_fill(&mut self, fill: Fill) {
+        self.fill = fill;
+    }
+
+    fn active(&self) -> bool {
+        !self.vertices.is_empty() && !self.finished
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let mut bounds = Bounds::from_points(&self.vertices)?;
+        if let Some(pointer) = self.pointer {
+            bounds = bounds.extend(pointer);
+        }
+        Some(bounds.grow(CLOSE_DISTANCE.max(self.line_style.width(self.line_width)) + 1.0))
+    }
+
+    fn handles(&self, _: Point) -> bool {
+        // every click while the line is open is a vertex
+        true
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        self.pointer = None;
+    }
+
+    // a single vertex is not a line yet
+    fn is_empty(&self) -> bool {
+        self.vertices.len() < 2
+    }
+
+    fn remove_last_point(&mut self) {
+        self.vertices.pop();
+        if self.vertices.is_empty() {
+            self.finish();
+        }
+    }
+}
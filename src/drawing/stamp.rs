@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+
+use gtk::cairo::Context;
+use gtk::gdk::{prelude::GdkCairoContextExt, ModifierType};
+use gtk::gdk_pixbuf::Pixbuf;
+use gtk::pango;
+
+use crate::colors::{self, Color};
+use crate::error::Result;
+
+use super::drawing_tool::{draw_layout, saved, Bounds, DrawingTool, Point, TextStyle};
+
+/// An image from the stamps directory, loaded once and scaled when it is drawn.
+#[derive(Debug, Clone)]
+pub struct StampImage {
+    /// the file name without the extension
+    pub name: String,
+    /// the file it was loaded from, reloading the directory keeps the images by it
+    pub path: PathBuf,
+    pub pixbuf: Pixbuf,
+}
+
+/// What a stamp shows.
+#[derive(Debug, Clone)]
+pub enum StampSource {
+    Image(StampImage),
+    /// emoji or other characters, laid out with Pango so color emoji fonts are used. Symbols
+    /// without colors of their own take the current color.
+    Symbol(String),
+}
+
+/// A stamp image or symbol placed with a click. It stays editable until the next click
+/// elsewhere or `element.finish`: dragging it moves it and the width actions, scrolling by
+/// default, resize it.
+pub struct Stamp {
+    center: Option<Point>,
+    source: Option<StampSource>,
+    text_style: Option<TextStyle>,
+    line_width: f64,
+    color: Color,
+    // where the stamp was grabbed, relative to its center
+    grab: Option<Point>,
+    finished: bool,
+}
+
+impl Stamp {
+    pub fn new() -> Stamp {
+        Stamp {
+            center: None,
+            source: None,
+            text_style: None,
+            line_width: 2.0,
+            color: colors::RED,
+            grab: None,
+            finished: false,
+        }
+    }
+
+    // the longer side of the stamp, it grows with the line width like the step badges do
+    fn size(&self) -> f64 {
+        16.0 + self.line_width * 24.0
+    }
+
+    // the symbol in a font as big as the stamp
+    fn layout(&self, symbol: &str) -> Option<pango::Layout> {
+        let style = self.text_style.as_ref()?;
+        let mut font = style.font.clone();
+        font.set_absolute_size(self.size() * pango::SCALE as f64);
+        let layout = pango::Layout::new(&style.context);
+        layout.set_font_description(Some(&font));
+        layout.set_text(symbol);
+        Some(layout)
+    }
+
+    // the image scaled to the size of the stamp, or the laid out symbol, around its center
+    fn area(&self) -> Option<(Bounds, f64)> {
+        let center = self.center?;
+        let (width, height, scale) = match self.source.as_ref()? {
+            StampSource::Image(image) => {
+                let (width, height) = (image.pixbuf.width() as f64, image.pixbuf.height() as f64);
+                (width, height, self.size() / width.max(height).max(1.0))
+            }
+            StampSource::Symbol(symbol) => {
+                let (width, height) = self.layout(symbol)?.pixel_size();
+                (width as f64, height as f64, 1.0)
+            }
+        };
+        let half = Point(width, height) * (scale / 2.0);
+        Some((
+            Bounds {
+                min: center - half,
+                max: center + half,
+            },
+            scale,
+        ))
+    }
+}
+
+impl Default for Stamp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawingTool for Stamp {
+    fn release_mouse(&mut self, point: Point) {
+        self.motion_notify(point, ModifierType::empty());
+        self.grab = None;
+    }
+
+    fn press_mouse(&mut self, point: Point, _: ModifierType) {
+        match self.center {
+            Some(center) => self.grab = Some(point - center),
+            None => {
+                self.center = Some(point);
+                self.grab = Some(Point(0.0, 0.0));
+            }
+        }
+    }
+
+    fn motion_notify(&mut self, point: Point, _: ModifierType) {
+        if let Some(grab) = self.grab.filter(|_| !self.finished) {
+            self.center = Some(point - grab);
+        }
+    }
+
+    fn draw(&self, cnx: &Context) -> Result<()> {
+        let (Some((area, scale)), Some(source)) = (self.area(), &self.source) else {
+            return Ok(());
+        };
+        match source {
+            StampSource::Image(image) => saved(cnx, || {
+                cnx.translate(area.min.0, area.min.1);
+                cnx.scale(scale, scale);
+                cnx.set_source_pixbuf(&image.pixbuf, 0.0, 0.0);
+                cnx.paint()?;
+                Ok(())
+            })?,
+            StampSource::Symbol(symbol) => {
+                if let Some(layout) = self.layout(symbol) {
+                    draw_layout(cnx, &layout, self.color, area.min)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        self.line_width = width;
+    }
+
+    // images keep their own colors, only symbols use it
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_text_style(&mut self, style: TextStyle) {
+        self.text_style = Some(style);
+    }
+
+    fn set_stamp(&mut self, source: StampSource) {
+        self.source = Some(source);
+    }
+
+    fn active(&self) -> bool {
+        self.center.is_some() && !self.finished
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        Some(self.area()?.0.grow(1.0))
+    }
+
+    fn handles(&self, point: Point) -> bool {
+        self.area().is_some_and(|(area, _)| area.contains(point))
+    }
+
+    fn finish(&mut self) {
+        self.grab = None;
+        self.finished = true;
+    }
+}
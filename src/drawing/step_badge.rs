@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use gtk::cairo::Context;
+use gtk::gdk::ModifierType;
+use gtk::pango;
+use serde::{Deserialize, Serialize};
+
+use crate::colors::{self, Color};
+use crate::error::Result;
+
+use super::drawing_tool::{draw_layout, Bounds, DrawingTool, Point, TextStyle};
+
+/// How step badges count, `step.cycle` goes through them in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStyle {
+    #[default]
+    Numbers,
+    /// A to Z, then AA, AB and so on
+    Letters,
+    Roman,
+}
+
+impl StepStyle {
+    pub fn next(self) -> StepStyle {
+        match self {
+            StepStyle::Numbers => StepStyle::Letters,
+            StepStyle::Letters => StepStyle::Roman,
+            StepStyle::Roman => StepStyle::Numbers,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StepStyle::Numbers => "numbers",
+            StepStyle::Letters => "letters",
+            StepStyle::Roman => "roman",
+        }
+    }
+
+    /// the label of step `number`, counting from 1
+    pub fn label(self, number: usize) -> String {
+        match self {
+            StepStyle::Numbers => number.to_string(),
+            StepStyle::Letters => {
+                let mut label = Vec::new();
+                let mut rest = number;
+                while rest > 0 {
+                    rest -= 1;
+                    label.push(char::from(b'A' + (rest % 26) as u8));
+                    rest /= 26;
+                }
+                label.into_iter().rev().collect()
+            }
+            StepStyle::Roman => {
+                const NUMERALS: [(usize, &str); 13] = [
+                    (1000, "M"),
+                    (900, "CM"),
+                    (500, "D"),
+                    (400, "CD"),
+                    (100, "C"),
+                    (90, "XC"),
+                    (50, "L"),
+                    (40, "XL"),
+                    (10, "X"),
+                    (9, "IX"),
+                    (5, "V"),
+                    (4, "IV"),
+                    (1, "I"),
+                ];
+                let mut label = String::new();
+                let mut rest = number;
+                for (value, numeral) in NUMERALS {
+                    while rest >= value {
+                        label.push_str(numeral);
+                        rest -= value;
+                    }
+                }
+                label
+            }
+        }
+    }
+}
+
+/// Give the badges of each series the numbers of their order in `elements`, after one was
+/// removed or to number a new one.
+pub fn renumber(elements: &mut [Box<dyn DrawingTool>]) {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for element in elements {
+        if let Some(series) = element.step_series() {
+            let count = counts.entry(series).or_default();
+            *count += 1;
+            element.set_step_number(*count);
+        }
+    }
+}
+
+/// A filled circle with the number of a step in it, placed with a click and moved while the
+/// button is held. Badges belong to a series, `step.reset` starts a new one that counts from 1
+/// again.
+pub struct StepBadge {
+    center: Option<Point>,
+    series: usize,
+    number: usize,
+    step_style: StepStyle,
+    text_style: Option<TextStyle>,
+    finished: bool,
+    line_width: f64,
+    color: Color,
+}
+
+impl StepBadge {
+    pub fn new() -> StepBadge {
+        StepBadge {
+            center: None,
+            series: 0,
+            number: 1,
+            step_style: StepStyle::Numbers,
+            text_style: None,
+            finished: false,
+            line_width: 2.0,
+            color: colors::RED,
+        }
+    }
+
+    // the badge grows with the line width so it matches what is drawn around it
+    fn radius(&self) -> f64 {
+        8.0 + self.line_width * 3.0
+    }
+
+    // white on dark colors and black on light ones
+    fn label_color(&self) -> Color {
+        let color = self.color;
+        let luminance = 0.299 * color.red() + 0.587 * color.green() + 0.114 * color.blue();
+        let label = if luminance > 0.6 { 0.0 } else { 1.0 };
+        Color::new(label, label, label, color.alpha())
+    }
+}
+
+impl Default for StepBadge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawingTool for StepBadge {
+    fn release_mouse(&mut self, point: Point) {
+        self.center = Some(point);
+        self.finished = true;
+    }
+
+    fn press_mouse(&mut self, point: Point, _: ModifierType) {
+        self.center = Some(point);
+    }
+
+    fn motion_notify(&mut self, point: Point, _: ModifierType) {
+        if self.active() {
+            self.center = Some(point);
+        }
+    }
+
+    fn draw(&self, cnx: &Context) -> Result<()> {
+        let Some(center) = self.center else {
+            return Ok(());
+        };
+        let radius = self.radius();
+        let color = self.color;
+        cnx.arc(center.0, center.1, radius, 0.0, 2.0 * PI);
+        cnx.set_source_rgba(
+            color.red().into(),
+            color.green().into(),
+            color.blue().into(),
+            color.alpha().into(),
+        );
+        cnx.fill()?;
+
+        let Some(style) = &self.text_style else {
+            return Ok(());
+        };
+        let mut font = style.font.clone();
+        font.set_weight(pango::Weight::Bold);
+        let label = self.step_style.label(self.number);
+        // longer labels get a smaller font so they stay inside the circle
+        let size = radius * 1.1 / (label.chars().count().max(2) as f64 / 2.0).sqrt();
+        font.set_absolute_size(size * pango::SCALE as f64);
+        let layout = pango::Layout::new(&style.context);
+        layout.set_font_description(Some(&font));
+        layout.set_text(&label);
+        let (width, height) = layout.pixel_size();
+        let origin = center - Point(width as f64, height as f64) / 2.0;
+        draw_layout(cnx, &layout, self.label_color(), origin)
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        self.line_width = width;
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    // badges have no outline
+    fn set_text_style(&mut self, style: TextStyle) {
+        self.text_style = Some(style);
+    }
+
+    fn set_step(&mut self, series: usize, style: StepStyle) {
+        self.series = series;
+        self.step_style = style;
+    }
+
+    fn step_series(&self) -> Option<usize> {
+        Some(self.series)
+    }
+
+    fn set_step_number(&mut self, number: usize) {
+        self.number = number;
+    }
+
+    fn active(&self) -> bool {
+        self.center.is_some() && !self.finished
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let center = self.center?;
+        Some(Bounds::from_points(&[center])?.grow(self.radius() + 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    // an element that only remembers the number `renumber` gave it
+    struct Numbered {
+        series: Option<usize>,
+        number: Rc<Cell<usize>>,
+    }
+
+    impl DrawingTool for Numbered {
+        fn release_mouse(&mut self, _point: Point) {}
+        fn press_mouse(&mut self, _point: Point, _modifiers: ModifierType) {}
+        fn motion_notify(&mut self, _point: Point, _modifiers: ModifierType) {}
+        fn draw(&self, _cnx: &Context) -> Result<()> {
+            Ok(())
+        }
+        fn set_line_width(&mut self, _width: f64) {}
+        fn set_color(&mut self, _color: Color) {}
+        fn step_series(&self) -> Option<usize> {
+            self.series
+        }
+        fn set_step_number(&mut self, number: usize) {
+            self.number.set(number);
+        }
+        fn active(&self) -> bool {
+            false
+        }
+        fn bounds(&self) -> Option<Bounds> {
+            None
+        }
+    }
+
+    #[test]
+    fn letters_go_on_past_z() {
+        let label = |number| StepStyle::Letters.label(number);
+        assert_eq!(label(1), "A");
+        assert_eq!(label(26), "Z");
+        assert_eq!(label(27), "AA");
+        assert_eq!(label(52), "AZ");
+        assert_eq!(label(53), "BA");
+        assert_eq!(label(702), "ZZ");
+        assert_eq!(label(703), "AAA");
+        assert_eq!(StepStyle::Roman.label(1994), "MCMXCIV");
+        assert_eq!(StepStyle::Numbers.label(12), "12");
+    }
+
+    #[test]
+    fn series_are_numbered_apart() {
+        let numbers: Vec<_> = (0..5).map(|_| Rc::new(Cell::new(0))).collect();
+        let series = [Some(0), Some(1), None, Some(0), Some(1)];
+        let mut elements: Vec<Box<dyn DrawingTool>> = series
+            .iter()
+            .zip(&numbers)
+            .map(|(series, number)| {
+                Box::new(Numbered {
+                    series: *series,
+                    number: number.clone(),
+                }) as Box<dyn DrawingTool>
+            })
+            .collect();
+        renumber(&mut elements);
+        let numbered =
+            |numbers: &[Rc<Cell<usize>>]| numbers.iter().map(|n| n.get()).collect::<Vec<_>>();
+        assert_eq!(numbered(&numbers), [1, 1, 0, 2, 2]);
+
+        // the badges after a removed one move up
+        elements.remove(0);
+        renumber(&mut elements);
+        assert_eq!(numbered(&numbers[1..]), [1, 0, 1, 2]);
+    }
+}
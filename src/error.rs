@@ -0,0 +1,85 @@
+use std::fmt;
+
+use gtk::{cairo, glib};
+
+/// Everything that can go wrong in chicolli without being a reason to take the overlay down.
+/// These errors are logged and the affected part (an element, a cursor, the config) falls back
+/// to something sensible.
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Config(serde_json::Error),
+    TomlConfig(toml::de::Error),
+    Cairo(cairo::Error),
+    Surface(cairo::BorrowError),
+    Image(glib::Error),
+    NoConfigDir,
+    NoStateDir,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {e}"),
+            Error::Config(e) => write!(f, "invalid config: {e}"),
+            Error::TomlConfig(e) => write!(f, "invalid config: {e}"),
+            Error::Cairo(e) => write!(f, "drawing error: {e}"),
+            Error::Surface(e) => write!(f, "surface error: {e}"),
+            Error::Image(e) => write!(f, "image error: {e}"),
+            Error::NoConfigDir => write!(f, "could not find default config directory"),
+            Error::NoStateDir => write!(f, "could not find a directory for the state"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Config(e) => Some(e),
+            Error::TomlConfig(e) => Some(e),
+            Error::Cairo(e) => Some(e),
+            Error::Surface(e) => Some(e),
+            Error::Image(e) => Some(e),
+            Error::NoConfigDir | Error::NoStateDir => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::TomlConfig(e)
+    }
+}
+
+impl From<cairo::Error> for Error {
+    fn from(e: cairo::Error) -> Self {
+        Error::Cairo(e)
+    }
+}
+
+impl From<cairo::BorrowError> for Error {
+    fn from(e: cairo::BorrowError) -> Self {
+        Error::Surface(e)
+    }
+}
+
+impl From<glib::Error> for Error {
+    fn from(e: glib::Error) -> Self {
+        Error::Image(e)
+    }
+}
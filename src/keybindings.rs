@@ -0,0 +1,488 @@
+use std::collections::{BTreeMap, HashMap};
+
+use gtk::gdk::{Key, ModifierType};
+
+use crate::colors;
+use crate::config::{Configuration, KeyList};
+use crate::drawing::drawing_tool::CurrentDrawingTool;
+
+/// Everything that can be bound to a key, mouse button or scroll direction in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// draw with the current tool, only meaningful for mouse buttons
+    Draw,
+    ToolPencil,
+    ToolArrow,
+    ToolReverseArrow,
+    ToolCurvedArrow,
+    ToolRectangle,
+    ToolPolyline,
+    ToolCallout,
+    ToolStep,
+    /// start a new series of step badges, counting from 1
+    StepReset,
+    /// count the step badges in the next style, from 1 again in a new series
+    StepCycle,
+    /// remove the step badge under the pointer, the ones after it count down. This is the only
+    /// way to delete a badge once it is placed.
+    StepRemove,
+    ToolStamp,
+    /// select the next stamp image and the stamp tool
+    StampNext,
+    StampPrevious,
+    StampPicker,
+    /// pick an emoji or other symbol to stamp
+    StampSymbols,
+    /// end the element that is still being edited, e.g. a polyline
+    ElementFinish,
+    ElementRemovePoint,
+    OverlayDisable,
+    OverlayMenu,
+    OverlayQuit,
+    /// select the palette color at this index, bound by the `key` of the palette entry
+    PaletteColor(usize),
+    /// select the preset at this index, bound by the `key` of the preset
+    Preset(usize),
+    ColorNext,
+    ColorPrevious,
+    ColorChooser,
+    OpacityIncrease,
+    OpacityDecrease,
+    LightnessIncrease,
+    LightnessDecrease,
+    HueIncrease,
+    HueDecrease,
+    WidthIncrease,
+    WidthDecrease,
+    FillCycle,
+    LineStyleCycle,
+    ProfileNext,
+    ProfilePrevious,
+}
+
+// the names actions have in the config
+const ACTIONS: &[(&str, Action)] = &[
+    ("draw", Action::Draw),
+    ("tool.pencil", Action::ToolPencil),
+    ("tool.arrow", Action::ToolArrow),
+    ("tool.reverse_arrow", Action::ToolReverseArrow),
+    ("tool.curved_arrow", Action::ToolCurvedArrow),
+    ("tool.rectangle", Action::ToolRectangle),
+    ("tool.polyline", Action::ToolPolyline),
+    ("tool.callout", Action::ToolCallout),
+    ("tool.step", Action::ToolStep),
+    ("step.reset", Action::StepReset),
+    ("step.cycle", Action::StepCycle),
+    ("step.remove", Action::StepRemove),
+    ("tool.stamp", Action::ToolStamp),
+    ("stamp.next", Action::StampNext),
+    ("stamp.previous", Action::StampPrevious),
+    ("stamp.picker", Action::StampPicker),
+    ("stamp.symbols", Action::StampSymbols),
+    ("element.finish", Action::ElementFinish),
+    ("element.remove_point", Action::ElementRemovePoint),
+    ("overlay.disable", Action::OverlayDisable),
+    ("overlay.menu", Action::OverlayMenu),
+    ("overlay.quit", Action::OverlayQuit),
+    ("color.next", Action::ColorNext),
+    ("color.previous", Action::ColorPrevious),
+    ("color.chooser", Action::ColorChooser),
+    ("opacity.increase", Action::OpacityIncrease),
+    ("opacity.decrease", Action::OpacityDecrease),
+    ("lightness.increase", Action::LightnessIncrease),
+    ("lightness.decrease", Action::LightnessDecrease),
+    ("hue.increase", Action::HueIncrease),
+    ("hue.decrease", Action::HueDecrease),
+    ("width.increase", Action::WidthIncrease),
+    ("width.decrease", Action::WidthDecrease),
+    ("fill.cycle", Action::FillCycle),
+    ("line_style.cycle", Action::LineStyleCycle),
+    ("profile.next", Action::ProfileNext),
+    ("profile.previous", Action::ProfilePrevious),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|(_, action)| *action)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::PaletteColor(_) => return "a palette color",
+            Action::Preset(_) => return "a preset",
+            _ => (),
+        }
+        ACTIONS
+            .iter()
+            .find(|(_, action)| action == self)
+            .map(|(name, _)| *name)
+            .unwrap_or("unknown")
+    }
+
+    /// the tool a `tool.*` action selects, bound to a mouse button it draws with that tool
+    pub fn tool(&self) -> Option<CurrentDrawingTool> {
+        match self {
+            Action::ToolPencil => Some(CurrentDrawingTool::NormalLine),
+            Action::ToolArrow => Some(CurrentDrawingTool::NormalArrowHeadPointer),
+            Action::ToolReverseArrow => Some(CurrentDrawingTool::NormalArrowHeadBase),
+            Action::ToolCurvedArrow => Some(CurrentDrawingTool::CurvedArrow),
+            Action::ToolRectangle => Some(CurrentDrawingTool::NormalRectangle),
+            Action::ToolPolyline => Some(CurrentDrawingTool::Polyline),
+            Action::ToolCallout => Some(CurrentDrawingTool::Callout),
+            Action::ToolStep => Some(CurrentDrawingTool::StepBadge),
+            Action::ToolStamp => Some(CurrentDrawingTool::Stamp),
+            _ => None,
+        }
+    }
+
+    /// the tool called `name` in the config, the name of its `tool.*` action without the prefix
+    pub fn tool_by_name(name: &str) -> Option<CurrentDrawingTool> {
+        Action::from_name(&format!("tool.{name}"))?.tool()
+    }
+}
+
+// only these modifiers take part in matching, so e.g. caps lock does not break bindings
+const MODIFIERS_MASK: ModifierType = ModifierType::SHIFT_MASK
+    .union(ModifierType::CONTROL_MASK)
+    .union(ModifierType::ALT_MASK)
+    .union(ModifierType::SUPER_MASK)
+    .union(ModifierType::HYPER_MASK)
+    .union(ModifierType::META_MASK);
+
+/// strip leading modifiers like `<Ctrl><Shift>` and return them together with the rest
+pub fn split_modifiers(accelerator: &str) -> Result<(ModifierType, &str), String> {
+    let mut modifiers = ModifierType::empty();
+    let mut rest = accelerator.trim();
+    while let Some(stripped) = rest.strip_prefix('<') {
+        let Some((name, after)) = stripped.split_once('>') else {
+            return Err(format!("unclosed modifier in \"{accelerator}\""));
+        };
+        modifiers |= match name.to_lowercase().as_str() {
+            "ctrl" | "control" | "primary" => ModifierType::CONTROL_MASK,
+            "shift" => ModifierType::SHIFT_MASK,
+            "alt" | "mod1" => ModifierType::ALT_MASK,
+            "super" => ModifierType::SUPER_MASK,
+            "hyper" => ModifierType::HYPER_MASK,
+            "meta" => ModifierType::META_MASK,
+            _ => return Err(format!("unknown modifier <{name}> in \"{accelerator}\"")),
+        };
+        rest = after;
+    }
+    Ok((modifiers, rest))
+}
+
+/// A key together with the modifiers that have to be held, e.g. `<Ctrl><Shift>z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Accelerator {
+    key: Key,
+    modifiers: ModifierType,
+}
+
+impl Accelerator {
+    pub fn parse(accelerator: &str) -> Result<Accelerator, String> {
+        let (modifiers, name) = split_modifiers(accelerator)?;
+        let key = Key::from_name(name)
+            .ok_or_else(|| format!("unknown key name \"{name}\" in \"{accelerator}\""))?;
+        Ok(Accelerator::from_event(key, modifiers))
+    }
+
+    /// normalized so that the parsed `<Shift>z` matches the `Z` a key press with shift reports
+    pub fn from_event(key: Key, modifiers: ModifierType) -> Accelerator {
+        Accelerator {
+            key: key.to_lower(),
+            modifiers: modifiers & MODIFIERS_MASK,
+        }
+    }
+}
+
+/// What a mouse binding reacts to, written as `button1`, `scroll-up`, `scroll-down`,
+/// `scroll-left` or `scroll-right` after the modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseTrigger {
+    Button(u32),
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MouseAccelerator {
+    trigger: MouseTrigger,
+    modifiers: ModifierType,
+}
+
+impl MouseAccelerator {
+    pub fn parse(accelerator: &str) -> Result<MouseAccelerator, String> {
+        let (modifiers, name) = split_modifiers(accelerator)?;
+        let trigger = match name {
+            "scroll-up" => MouseTrigger::ScrollUp,
+            "scroll-down" => MouseTrigger::ScrollDown,
+            "scroll-left" => MouseTrigger::ScrollLeft,
+            "scroll-right" => MouseTrigger::ScrollRight,
+            _ => name
+                .strip_prefix("button")
+                .and_then(|button| button.parse().ok())
+                .filter(|button| *button > 0)
+                .map(MouseTrigger::Button)
+                .ok_or_else(|| format!("unknown mouse binding \"{name}\" in \"{accelerator}\""))?,
+        };
+        Ok(MouseAccelerator::from_event(trigger, modifiers))
+    }
+
+    pub fn from_event(trigger: MouseTrigger, modifiers: ModifierType) -> MouseAccelerator {
+        MouseAccelerator {
+            trigger,
+            modifiers: modifiers & MODIFIERS_MASK,
+        }
+    }
+}
+
+/// Something wrong with one entry of a bindings section of the config.
+#[derive(Debug)]
+pub struct BindingProblem {
+    /// the action name the entry is listed under
+    pub action: String,
+    /// position of the binding when the action has a list of them
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+// parse one bindings section of the config, unknown actions, unparseable bindings and bindings
+// used more than once are skipped and returned as problems
+fn parse_bindings<T: Copy + Eq + std::hash::Hash>(
+    config: &BTreeMap<String, KeyList>,
+    parse: fn(&str) -> Result<T, String>,
+) -> (HashMap<T, Action>, Vec<BindingProblem>) {
+    let mut bindings = HashMap::new();
+    let mut problems = Vec::new();
+    for (name, keys) in config {
+        let Some(action) = Action::from_name(name) else {
+            problems.push(BindingProblem {
+                action: name.clone(),
+                index: None,
+                message: format!("unknown action \"{name}\""),
+            });
+            continue;
+        };
+        let listed = matches!(keys, KeyList::Many(_));
+        for (index, key) in keys.iter().enumerate() {
+            let mut problem = |message| {
+                problems.push(BindingProblem {
+                    action: name.clone(),
+                    index: listed.then_some(index),
+                    message,
+                })
+            };
+            let binding = match parse(key) {
+                Ok(binding) => binding,
+                Err(e) => {
+                    problem(e);
+                    continue;
+                }
+            };
+            match bindings.get(&binding) {
+                Some(other) if *other != action => problem(already_bound(key, other)),
+                Some(_) => problem(format!("\"{key}\" is listed twice")),
+                None => {
+                    bindings.insert(binding, action);
+                }
+            }
+        }
+    }
+    (bindings, problems)
+}
+
+// the first binding of a key wins
+fn already_bound(key: &str, other: &Action) -> String {
+    format!(
+        "\"{key}\" is already bound to {}, ignoring this binding",
+        other.name()
+    )
+}
+
+// bind the keys listed with the palette colors and the presets, keys the keybindings section
+// already uses are skipped and returned as problems with the section and index of the entry
+fn bind_entry_keys(
+    bindings: &mut HashMap<Accelerator, Action>,
+    config: &Configuration,
+) -> Vec<(&'static str, usize, String)> {
+    // `Palette` leaves out the colors it can not parse, the keys select by the position in it
+    let palette = config
+        .palette
+        .iter()
+        .flatten()
+        .enumerate()
+        .filter(|(_, entry)| colors::parse(&entry.color).is_ok())
+        .enumerate()
+        .map(|(position, (index, entry))| {
+            ("palette", index, &entry.key, Action::PaletteColor(position))
+        });
+    let presets = config
+        .presets
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, entry)| ("presets", index, &entry.key, Action::Preset(index)));
+
+    let mut problems = Vec::new();
+    for (section, index, key, action) in palette.chain(presets) {
+        let Some(key) = key else {
+            continue;
+        };
+        match Accelerator::parse(key) {
+            Ok(accelerator) => match bindings.get(&accelerator) {
+                Some(other) => problems.push((section, index, already_bound(key, other))),
+                None => {
+                    bindings.insert(accelerator, action);
+                }
+            },
+            Err(e) => problems.push((section, index, e)),
+        }
+    }
+    problems
+}
+
+/// problems with the keys of the palette colors and presets, by section and index of the entry
+pub fn check_entry_keys(config: &Configuration) -> Vec<(&'static str, usize, String)> {
+    let mut bindings = match &config.keybindings {
+        Some(keybindings) => parse_bindings(keybindings, Accelerator::parse).0,
+        None => HashMap::new(),
+    };
+    bind_entry_keys(&mut bindings, config)
+}
+
+/// problems in the `keybindings` section, without building the bindings
+pub fn check_keybindings(config: &BTreeMap<String, KeyList>) -> Vec<BindingProblem> {
+    parse_bindings(config, Accelerator::parse).1
+}
+
+/// problems in the `mousebindings` section, without building the bindings
+pub fn check_mousebindings(config: &BTreeMap<String, KeyList>) -> Vec<BindingProblem> {
+    parse_bindings(config, MouseAccelerator::parse).1
+}
+
+/// The keybindings of the config, parsed once at startup.
+pub struct Keybindings {
+    bindings: HashMap<Accelerator, Action>,
+}
+
+impl Keybindings {
+    /// the `keybindings` section and the keys of the palette colors and presets, entries with
+    /// problems are skipped, `config::validate` reports them
+    pub fn new(config: &Configuration) -> Keybindings {
+        let mut bindings = match &config.keybindings {
+            Some(keybindings) => parse_bindings(keybindings, Accelerator::parse).0,
+            None => HashMap::new(),
+        };
+        bind_entry_keys(&mut bindings, config);
+        Keybindings { bindings }
+    }
+
+    /// the action bound to `key`, `consumed` are the modifiers the keyboard layout used to
+    /// produce it. A binding with them wins, so `<Shift>p` still matches, otherwise they are
+    /// left out so e.g. `question` or the digits of an AZERTY layout match without Shift.
+    pub fn action(
+        &self,
+        key: Key,
+        modifiers: ModifierType,
+        consumed: ModifierType,
+    ) -> Option<Action> {
+        self.bindings
+            .get(&Accelerator::from_event(key, modifiers))
+            .or_else(|| {
+                self.bindings
+                    .get(&Accelerator::from_event(key, modifiers - consumed))
+            })
+            .copied()
+    }
+}
+
+/// The mouse button and scroll bindings of the config, parsed once at startup.
+pub struct MouseBindings {
+    bindings: HashMap<MouseAccelerator, Action>,
+}
+
+impl MouseBindings {
+    /// entries with problems are skipped, `config::validate` reports them
+    pub fn new(config: &BTreeMap<String, KeyList>) -> MouseBindings {
+        MouseBindings {
+            bindings: parse_bindings(config, MouseAccelerator::parse).0,
+        }
+    }
+
+    /// the action bound to `trigger` with exactly these modifiers. Without such a binding a
+    /// button still draws with what it is bound to unmodified, the tools use Shift, Ctrl and
+    /// Alt held at the press to constrain the shape.
+    pub fn action(&self, trigger: MouseTrigger, modifiers: ModifierType) -> Option<Action> {
+        let exact = self
+            .bindings
+            .get(&MouseAccelerator::from_event(trigger, modifiers))
+            .copied();
+        if exact.is_some() || !matches!(trigger, MouseTrigger::Button(_)) {
+            return exact;
+        }
+        self.bindings
+            .get(&MouseAccelerator::from_event(
+                trigger,
+                ModifierType::empty(),
+            ))
+            .copied()
+            .filter(|action| *action == Action::Draw || action.tool().is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers_are_split_off() {
+        assert_eq!(
+            split_modifiers(" <Ctrl><shift>z"),
+            Ok((ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK, "z"))
+        );
+        assert_eq!(
+            split_modifiers("<Control><Primary>z"),
+            Ok((ModifierType::CONTROL_MASK, "z"))
+        );
+        assert_eq!(
+            split_modifiers("<Mod1>button2"),
+            Ok((ModifierType::ALT_MASK, "button2"))
+        );
+        assert_eq!(split_modifiers("plus"), Ok((ModifierType::empty(), "plus")));
+        assert!(split_modifiers("<Ctrl z").is_err());
+        assert!(split_modifiers("<Fn>z").is_err());
+    }
+
+    #[test]
+    fn mouse_accelerators() {
+        assert_eq!(
+            MouseAccelerator::parse("button2"),
+            Ok(MouseAccelerator::from_event(
+                MouseTrigger::Button(2),
+                ModifierType::empty()
+            ))
+        );
+        assert_eq!(
+            MouseAccelerator::parse("<Shift>scroll-up"),
+            Ok(MouseAccelerator::from_event(
+                MouseTrigger::ScrollUp,
+                ModifierType::SHIFT_MASK
+            ))
+        );
+        assert_eq!(
+            MouseAccelerator::parse("<Ctrl>scroll-right"),
+            Ok(MouseAccelerator::from_event(
+                MouseTrigger::ScrollRight,
+                ModifierType::CONTROL_MASK
+            ))
+        );
+        for invalid in ["button0", "button", "buttonx", "scroll", "<Ctrl>"] {
+            assert!(MouseAccelerator::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+}
@@ -1,21 +1,106 @@
-use drawing::drawing_tool::DrawingTool;
+use drawing::drawing_tool::{Bounds, DrawingTool, Point, TextStyle};
+use keybindings::Action;
 
-use gio::Cancellable;
 use gtk::glib::{self, Propagation};
-use gtk::{
-    cairo::Region,
-    gdk::{Display, Key},
-    prelude::*,
-};
+use gtk::{cairo::Region, gdk::Display, prelude::*};
 
 use std::{cell::RefCell, rc::Rc};
 
+pub mod app;
+pub mod cli;
+pub mod color_popover;
 pub mod colors;
 pub mod config;
+pub mod cursors;
 pub mod drawing;
+pub mod error;
+pub mod keybindings;
+pub mod presets;
+pub mod stamp_popover;
+pub mod stamps;
+pub mod state;
+pub mod swatch;
+pub mod symbol_popover;
+pub mod symbols;
+pub mod toast;
+pub mod watch;
+
+const NO_STAMPS: &str = "no stamps, add PNG or SVG images to the stamps directory";
+
+// place the live layer over the area the active element covers, so a pointer move only
+// damages that part of the overlay instead of the whole output
+fn track_damage(live: &gtk::DrawingArea, bounds: Option<Bounds>) {
+    let Some(bounds) = bounds else {
+        live.set_visible(false);
+        return;
+    };
+    // a layer bigger than the overlay would make the overlay bigger than the output
+    let limit = live
+        .parent()
+        .map(|overlay| Point(overlay.width() as f64, overlay.height() as f64))
+        .filter(|limit| limit.0 > 0.0 && limit.1 > 0.0)
+        .unwrap_or(Point(f64::INFINITY, f64::INFINITY));
+    let min = Point(
+        bounds.min.0.floor().clamp(0.0, (limit.0 - 1.0).max(0.0)),
+        bounds.min.1.floor().clamp(0.0, (limit.1 - 1.0).max(0.0)),
+    );
+    let bounds = Bounds {
+        min,
+        max: Point(
+            bounds.max.0.ceil().min(limit.0).max(min.0 + 1.0),
+            bounds.max.1.ceil().min(limit.1).max(min.1 + 1.0),
+        ),
+    };
+
+    // the layer only grows while an element is edited, every resize is a relayout of the
+    // overlay and most moves stay inside what is already covered
+    let bounds = if live.is_visible() {
+        let (width, height) = live.size_request();
+        let start = Point(live.margin_start() as f64, live.margin_top() as f64);
+        let covered = Bounds {
+            min: start,
+            max: start + Point(width as f64, height as f64),
+        };
+        if covered.contains(bounds.min) && covered.contains(bounds.max) {
+            live.queue_draw();
+            return;
+        }
+        bounds.union(covered)
+    } else {
+        bounds
+    };
+    live.set_margin_start(bounds.min.0 as i32);
+    live.set_margin_top(bounds.min.1 as i32);
+    live.set_size_request(
+        (bounds.max.0 - bounds.min.0) as i32,
+        (bounds.max.1 - bounds.min.1) as i32,
+    );
+    live.set_visible(true);
+    live.queue_draw();
+}
+
+// the scale of the output the widget is on, fractional scales included
+fn output_scale(widget: &impl IsA<gtk::Widget>) -> f64 {
+    widget
+        .native()
+        .map_or(widget.scale_factor() as f64, |native| {
+            native.surface().scale()
+        })
+}
+
+// remove the last element when it was finished with nothing worth keeping, e.g. a callout
+// without text
+fn drop_empty(elements: &mut Vec<Box<dyn DrawingTool>>) {
+    if elements
+        .last()
+        .is_some_and(|elem| !elem.active() && elem.is_empty())
+    {
+        elements.pop();
+    }
+}
 
 // https://github.com/wmww/gtk-layer-shell/blob/master/examples/simple-example.c
-fn activate(application: &gtk::Application) {
+fn activate(application: &gtk::Application, options: &cli::Options) {
     // Create a normal GTK window however you like
     let window = gtk::ApplicationWindow::new(application);
 
@@ -24,7 +109,16 @@ fn activate(application: &gtk::Application) {
         window.surface().set_opaque_region(Some(&Region::create()));
     }));
 
-    let conf = Rc::new(config::get_config());
+    let config_path = options.config.clone();
+    let base_conf = config::get_config(config_path.as_deref());
+    let profile = options.profile.clone().filter(|name| {
+        let known = base_conf.has_profile(name);
+        if !known {
+            eprintln!("unknown profile \"{name}\", using the config without a profile");
+        }
+        known
+    });
+    let conf = base_conf.with_profile(profile.as_deref());
 
     // Before the window is first realized, set it up to be a layer surface
     gtk4_layer_shell::init_for_window(&window);
@@ -43,256 +137,394 @@ fn activate(application: &gtk::Application) {
         gtk4_layer_shell::set_anchor(&window, anchor, state);
     }
 
-    // main components
-    let elements: Rc<RefCell<Vec<Box<dyn DrawingTool>>>> = Rc::new(RefCell::new(Vec::new()));
-
-    let color = Rc::new(RefCell::new(colors::RED));
-
-    let current_tool = Rc::new(RefCell::new(
-        drawing::drawing_tool::CurrentDrawingTool::NormalLine,
-    ));
-
     let key_controller = gtk::EventControllerKey::new();
 
-    let color_dialog = Rc::new(
-        gtk::ColorDialog::builder()
+    // Set up a widget
+    let draw = gtk::DrawingArea::new();
+    // the element that is currently drawn gets its own small widget on top of `draw`, which
+    // follows the damaged region, events still go to `draw` below it
+    let live = gtk::DrawingArea::new();
+    live.set_can_target(false);
+    live.set_halign(gtk::Align::Start);
+    live.set_valign(gtk::Align::Start);
+    live.set_visible(false);
+
+    // the menu is a popover on the layer surface itself
+    let menu = gtk::Popover::new();
+    menu.set_parent(&draw);
+    menu.set_has_arrow(false);
+    let menu_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    menu.set_child(Some(&menu_box));
+    draw.connect_destroy(glib::clone!(@weak menu => move |_| menu.unparent()));
+
+    let palette = colors::Palette::new(conf.palette.as_deref().unwrap_or_default());
+    let app = Rc::new(app::App {
+        window: window.clone(),
+        draw: draw.clone(),
+        live: live.clone(),
+        menu: menu.clone(),
+        config_path,
+        elements: RefCell::new(Vec::new()),
+        backing: RefCell::new(drawing::backing::Backing::new()),
+        color: RefCell::new(palette.first()),
+        palette: RefCell::new(palette),
+        presets: RefCell::new(presets::Presets::new(
+            conf.presets.as_deref().unwrap_or_default(),
+        )),
+        current_tool: RefCell::new(drawing::drawing_tool::CurrentDrawingTool::NormalLine),
+        line_width: RefCell::new(conf.line_thickness.unwrap_or(2.0)),
+        line_style: RefCell::new(conf.line_style.unwrap_or_default()),
+        fill: RefCell::new(conf.fill()),
+        step_style: RefCell::new(conf.step_style.unwrap_or_default()),
+        step_series: RefCell::new(0),
+        stamps: RefCell::new(stamps::Stamps::load()),
+        state: RefCell::new(state::State::load()),
+        cursors: RefCell::new(cursors::Cursors::load(conf.cursor_size.unwrap_or(30))),
+        keybindings: RefCell::new(keybindings::Keybindings::new(&conf)),
+        mousebindings: RefCell::new(keybindings::MouseBindings::new(
+            conf.mousebindings.as_ref().unwrap_or(&Default::default()),
+        )),
+        pointer: RefCell::new(Point(0.0, 0.0)),
+        toast: toast::Toast::new(),
+        swatch: swatch::Swatch::new(),
+        color_dialog: gtk::ColorDialog::builder()
             .title("Choose color")
             .modal(true)
             .build(),
-    );
+        // the palette and recent colors, on the overlay itself
+        color_popover: color_popover::ColorPopover::new(&draw),
+        stamp_popover: stamp_popover::StampPopover::new(&draw),
+        symbol_popover: symbol_popover::SymbolPopover::new(&draw),
+        base_conf: RefCell::new(base_conf),
+        profile: RefCell::new(profile),
+        conf: RefCell::new(conf),
+    });
 
-    // get the tools cursors
-    let mut pencil_cur = gtk::gdk::Cursor::from_name("default", None);
-    let mut arrow_cur = gtk::gdk::Cursor::from_name("default", None);
-    let mut rectangle_cur = gtk::gdk::Cursor::from_name("default", None);
-
-    let cursors_loc = config::get_cursors_config_loc();
-    if let Some(curs_loc) = cursors_loc {
-        if curs_loc.as_path().exists() {
-            let paths = std::fs::read_dir(curs_loc.as_path());
-            if let Ok(paths) = paths {
-                for path in paths {
-                    if let Ok(path) = path {
-                        if let Some(file_name) = path.path().file_stem() {
-                            if let Some(file_name) = file_name.to_str() {
-                                let pixbuf = gtk::gdk_pixbuf::Pixbuf::from_file_at_scale(
-                                    path.path(),
-                                    30,
-                                    30,
-                                    true,
-                                )
-                                .unwrap();
-                                let cur_texture = gtk::gdk::Texture::for_pixbuf(&pixbuf);
-                                let cur =
-                                    Some(gtk::gdk::Cursor::from_texture(&cur_texture, 0, 0, None));
-
-                                match file_name {
-                                    config::PENCIL_CUR => {
-                                        pencil_cur = cur;
-                                    }
-                                    config::ARROW_CUR => {
-                                        arrow_cur = cur;
-                                    }
-                                    config::SQUARE_CUR => {
-                                        rectangle_cur = cur;
-                                    }
-                                    _ => (),
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    application.connect_shutdown(glib::clone!(@strong app => move |_| {
+        if let Err(e) = app.state.borrow().save() {
+            eprintln!("could not save the state, {e}");
         }
-    }
+    }));
 
-    // Set up a widget
-    let draw = gtk::DrawingArea::new();
-    // the default cursor should be the pencil one
-    if let Some(pencil_cur) = pencil_cur.clone() {
-        draw.set_cursor(Some(&pencil_cur));
+    app.refresh_cursor();
+
+    let menu_entries = [
+        ("Pencil", Action::ToolPencil),
+        ("Arrow", Action::ToolArrow),
+        ("Reverse arrow", Action::ToolReverseArrow),
+        ("Curved arrow", Action::ToolCurvedArrow),
+        ("Rectangle", Action::ToolRectangle),
+        ("Polyline", Action::ToolPolyline),
+        ("Callout", Action::ToolCallout),
+        ("Step badge", Action::ToolStep),
+        ("Stamps…", Action::StampPicker),
+        ("Symbols…", Action::StampSymbols),
+        ("Choose color…", Action::ColorChooser),
+        ("Disable drawing", Action::OverlayDisable),
+        ("Quit", Action::OverlayQuit),
+    ];
+    for (label, action) in menu_entries {
+        let entry = gtk::Button::with_label(label);
+        entry.add_css_class("flat");
+        entry.connect_clicked(glib::clone!(@weak menu, @strong app => move |_| {
+            menu.popdown();
+            app.run(action);
+        }));
+        menu_box.append(&entry);
     }
 
-    key_controller.connect_key_pressed(glib::clone!(@strong draw, @strong window as w, @strong color_dialog, @strong conf, @strong color, @strong current_tool => @default-return Propagation::Proceed, move |_, keyval, _, _| {
-        // close your eyes 
-        let _draw_key = Key::from_name(conf.draw_keybind.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-        let _arrow_key = Key::from_name(conf.arrow_keybind.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-        let _reverse_arrow_key = Key::from_name(conf.reverse_arrow_keybind.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-        let _rectangle_key = Key::from_name(conf.rectangle_keybind.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-        let _disable_drawing_key = Key::from_name(conf.disable_drawing.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-        let _color_r = Key::from_name(conf.color_r.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-        let _color_g = Key::from_name(conf.color_g.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-        let _color_b = Key::from_name(conf.color_b.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-        let _color_chooser = Key::from_name(conf.color_chooser.as_deref().unwrap_or("")).unwrap_or(Key::Abelowdot);
-
-        match keyval {
-            // TOOLS
-            _ if _draw_key == keyval => {
-                *current_tool.borrow_mut() = drawing::drawing_tool::CurrentDrawingTool::NormalLine;
-                if let Some(pencil_cur) = pencil_cur.clone() {
-                    draw.set_cursor(Some(&pencil_cur));
-                }
-            },
-            _ if _arrow_key == keyval => {
-                *current_tool.borrow_mut() = drawing::drawing_tool::CurrentDrawingTool::NormalArrowHeadPointer;
-                if let Some(arrow_cur) = arrow_cur.clone() {
-                    draw.set_cursor(Some(&arrow_cur));
-                }
+    key_controller.connect_key_pressed(glib::clone!(@strong app => @default-return Propagation::Proceed, move |controller, keyval, _, state| {
+        // text typed into e.g. a callout does not trigger the bindings
+        if let Some(elem) = app.elements.borrow_mut().last_mut().filter(|elem| elem.active()) {
+            if elem.key_press(keyval, state) {
+                track_damage(&app.live, elem.bounds());
+                return Propagation::Stop;
             }
-            _ if _reverse_arrow_key == keyval => {
-                *current_tool.borrow_mut() = drawing::drawing_tool::CurrentDrawingTool::NormalArrowHeadBase;
-                if let Some(arrow_cur) = arrow_cur.clone() {
-                    draw.set_cursor(Some(&arrow_cur));
-                }
-            }
-            _ if _rectangle_key == keyval => {
-                *current_tool.borrow_mut() = drawing::drawing_tool::CurrentDrawingTool::NormalRectangle;
-                if let Some(rectangle_cur) = rectangle_cur.clone() {
-                    draw.set_cursor(Some(&rectangle_cur));
-                }
-            },
-            _ if _disable_drawing_key == keyval => {
-                gtk4_layer_shell::set_keyboard_mode(&w, gtk4_layer_shell::KeyboardMode::None);
-                w.surface().set_input_region(&Region::create());
-                w.unmap();
-                w.map();
-            },
-            // colors
-            _ if _color_r == keyval =>  *color.borrow_mut() = colors::RED,
-            _ if _color_g == keyval =>  *color.borrow_mut() = colors::GREEN,
-            _ if _color_b == keyval =>  *color.borrow_mut() = colors::BLUE,
-            _ if _color_chooser == keyval => {
-                gtk4_layer_shell::set_layer(&w, gtk4_layer_shell::Layer::Bottom);
-                color_dialog.choose_rgba(
-                    None::<&gtk::Window>,
-                    Some(&gtk::gdk::RGBA::RED),
-                    None::<&Cancellable>,
-                    glib::clone!(@strong color, @weak w => move |c| match c {
-                        Ok(c) => {
-                            gtk4_layer_shell::set_layer(&w, gtk4_layer_shell::Layer::Overlay);
-                            *color.borrow_mut() = c;
-                        },
-                        Err(_) => {
-                            // Dismissed by user
-                            gtk4_layer_shell::set_layer(&w, gtk4_layer_shell::Layer::Overlay);
-                        }
-                    }),
-                    );
-            },
-            _ => (),
-        };
+        }
+        let consumed = controller
+            .current_event()
+            .and_then(|event| event.downcast::<gtk::gdk::KeyEvent>().ok())
+            .map_or(gtk::gdk::ModifierType::empty(), |event| event.consumed_modifiers());
+        let action = app.keybindings.borrow().action(keyval, state, consumed);
+        if let Some(action) = action {
+            app.run(action);
+        }
         Propagation::Proceed
     }));
 
+    let config_files = config::get_config_watch_locs(app.config_path.as_deref());
+    let cursors_dir = config::get_cursors_config_loc();
+    let stamps_dir = config::get_stamps_config_loc();
+    let mut monitors = watch::watch(
+        &config_files
+            .iter()
+            .map(|path| path.as_path())
+            .collect::<Vec<_>>(),
+        &[],
+        glib::clone!(@strong app => move || app.reload_config()),
+    );
+    // the cursors and stamps directories only reload what is in them
+    monitors.extend(watch::watch(
+        &[],
+        &cursors_dir.as_deref().into_iter().collect::<Vec<_>>(),
+        glib::clone!(@strong app => move || app.reload_cursors()),
+    ));
+    monitors.extend(watch::watch(
+        &[],
+        &stamps_dir.as_deref().into_iter().collect::<Vec<_>>(),
+        glib::clone!(@strong app => move || app.stamps.borrow_mut().reload()),
+    ));
+    window.connect_destroy(move |_| {
+        for monitor in &monitors {
+            monitor.cancel();
+        }
+    });
+
     // key controller is added to the window and not to the drawarea because there it does not
     // work
     window.add_controller(key_controller);
 
     let motion_controller = gtk::EventControllerMotion::new();
-    motion_controller.connect_motion(
-        glib::clone!(@weak draw, @strong elements => move |_, x, y| {
-            if let Some(elem) = elements.borrow_mut().last_mut() {
-                elem.motion_notify(drawing::drawing_tool::Point(x, y));
-                if elem.active() {
-                     draw.queue_draw();
-                }
+    motion_controller.connect_motion(glib::clone!(@strong app => move |controller, x, y| {
+        *app.pointer.borrow_mut() = Point(x, y);
+        if let Some(elem) = app.elements.borrow_mut().last_mut() {
+            elem.motion_notify(Point(x, y), controller.current_event_state());
+            if elem.active() {
+                track_damage(&app.live, elem.bounds());
             }
-        }),
-    );
+        }
+    }));
 
     draw.add_controller(motion_controller);
 
-    let right_click_mouse = gtk::GestureClick::new();
+    let click_mouse = gtk::GestureClick::new();
 
-    // Set the gestures button to the right mouse button (=3)
-    right_click_mouse.set_button(gtk::gdk::ffi::GDK_BUTTON_SECONDARY as u32);
+    // listen to every button, what a button does comes from the mouse bindings
+    click_mouse.set_button(0);
 
     // Assign your handler to an event of the gesture (e.g. the `pressed` event)
-    right_click_mouse.connect_pressed(|_, _, _, _| {
-        // exit the application
-        std::process::exit(1);
-    });
-
-    draw.add_controller(right_click_mouse);
-
-    let line_width = Rc::new(RefCell::new(conf.line_thickness.unwrap_or(2.0)));
-
-    let left_click_mouse = gtk::GestureClick::new();
-
-    // Set the gestures button to the right mouse button (=3)
-    left_click_mouse.set_button(gtk::gdk::ffi::GDK_BUTTON_PRIMARY as u32);
-
-    // Assign your handler to an event of the gesture (e.g. the `pressed` event)
-    left_click_mouse.connect_pressed(glib::clone!(@strong elements, @strong current_tool, @strong line_width => move |_, _, x, y| {
-        let mut drawing_tool: Box<dyn drawing::drawing_tool::DrawingTool> = match *current_tool.borrow() {
-            drawing::drawing_tool::CurrentDrawingTool::NormalLine => Box::new(drawing::normal_line::NormalLine::new()),
-            drawing::drawing_tool::CurrentDrawingTool::NormalArrowHeadBase => Box::new(drawing::arrow::NormalArrow::new(true)),
-            drawing::drawing_tool::CurrentDrawingTool::NormalArrowHeadPointer => Box::new(drawing::arrow::NormalArrow::new(false)),
-            drawing::drawing_tool::CurrentDrawingTool::NormalRectangle => Box::new(drawing::normal_rectangle::NormalRectangle::new()),
+    click_mouse.connect_pressed(glib::clone!(@strong app => move |gesture, n_press, x, y| {
+        let trigger = keybindings::MouseTrigger::Button(gesture.current_button());
+        let action = app.mousebindings.borrow().action(trigger, gesture.current_event_state());
+        let Some(action) = action else {
+            return;
+        };
+        // a button bound to a tool draws with it, everything else just runs the action
+        let tool = match action {
+            Action::Draw => Some(*app.current_tool.borrow()),
+            _ => action.tool(),
         };
-        drawing_tool.press_mouse(drawing::drawing_tool::Point(x, y));
-        drawing_tool.set_line_width(*line_width.borrow());
-        drawing_tool.set_color(*color.borrow());
-        elements.borrow_mut().push(drawing_tool);
+        let point = Point(x, y);
+        let modifiers = gesture.current_event_state();
+        match tool {
+            Some(tool) => {
+                // an element that is still being edited, e.g. a curved arrow waiting to be bent,
+                // either takes the press or is done
+                {
+                    let mut elements = app.elements.borrow_mut();
+                    if let Some(elem) = elements.last_mut().filter(|elem| elem.active()) {
+                        // the second click of a double click ends e.g. a polyline
+                        if n_press > 1 {
+                            elem.finish();
+                            drop_empty(&mut elements);
+                            track_damage(&app.live, None);
+                            app.draw.queue_draw();
+                            return;
+                        }
+                        if elem.handles(point) {
+                            elem.press_mouse(point, modifiers);
+                            return;
+                        }
+                        elem.finish();
+                        let taken = elem.takes_finishing_click();
+                        drop_empty(&mut elements);
+                        track_damage(&app.live, None);
+                        app.draw.queue_draw();
+                        if taken {
+                            return;
+                        }
+                    }
+                }
+                let stamp = app.stamps.borrow().selected();
+                if tool == drawing::drawing_tool::CurrentDrawingTool::Stamp && stamp.is_none() {
+                    app.toast.error(NO_STAMPS);
+                    return;
+                }
+                let conf = app.conf.borrow();
+                let mut drawing_tool = tool.create();
+                drawing_tool.press_mouse(point, modifiers);
+                drawing_tool.set_line_width(*app.line_width.borrow());
+                drawing_tool.set_line_style(*app.line_style.borrow());
+                drawing_tool.set_color(*app.color.borrow());
+                drawing_tool.set_arrow_heads(conf.arrow_heads());
+                drawing_tool.set_fill(*app.fill.borrow());
+                drawing_tool.set_corner_radius(conf.corner_radius(tool));
+                drawing_tool.set_text_style(TextStyle {
+                    context: app.draw.pango_context(),
+                    font: conf.callout_font(),
+                });
+                drawing_tool.set_step(*app.step_series.borrow(), *app.step_style.borrow());
+                if let Some(stamp) = stamp {
+                    drawing_tool.set_stamp(stamp);
+                }
+                let mut elements = app.elements.borrow_mut();
+                elements.push(drawing_tool);
+                drawing::step_badge::renumber(&mut elements);
+            },
+            None => app.run(action),
+        }
     }));
 
-    left_click_mouse.connect_released(glib::clone!(@strong elements => move |_, _, x, y| {
-        if let Some(elem) = elements.borrow_mut().last_mut() {
-            elem.release_mouse(drawing::drawing_tool::Point(x, y));
+    click_mouse.connect_released(glib::clone!(@strong app => move |_, _, x, y| {
+        let mut elements = app.elements.borrow_mut();
+        if let Some(elem) = elements.last_mut() {
+            if !elem.active() {
+                return;
+            }
+            elem.release_mouse(Point(x, y));
+            // some elements stay editable after the button is released
+            if elem.active() {
+                track_damage(&app.live, elem.bounds());
+                return;
+            }
         }
+        drop_empty(&mut elements);
+        // the element is finished now and moves from the live layer into the backing surface
+        track_damage(&app.live, None);
+        app.draw.queue_draw();
     }));
 
-    draw.add_controller(left_click_mouse);
+    draw.add_controller(click_mouse);
 
-    // scroll controller
-    let scroll_controller =
-        gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::BOTH_AXES);
+    // scroll controller, discrete so touchpads scroll in steps like a wheel does
+    let scroll_controller = gtk::EventControllerScroll::new(
+        gtk::EventControllerScrollFlags::BOTH_AXES | gtk::EventControllerScrollFlags::DISCRETE,
+    );
 
     scroll_controller.connect_scroll(
-        glib::clone!(@strong line_width => @default-return Propagation::Proceed, move |_, _,  scroll| {
-            let mut width = line_width.borrow_mut();
-            let new_width = *width - scroll;
-            if new_width as i32 >= 1 {
-                *width = new_width;
-            } else {
-                *width = 1.0;
+        glib::clone!(@strong app => @default-return Propagation::Proceed, move |controller, dx, dy| {
+            let trigger = match (dx, dy) {
+                (_, dy) if dy < 0.0 => keybindings::MouseTrigger::ScrollUp,
+                (_, dy) if dy > 0.0 => keybindings::MouseTrigger::ScrollDown,
+                (dx, _) if dx < 0.0 => keybindings::MouseTrigger::ScrollLeft,
+                (dx, _) if dx > 0.0 => keybindings::MouseTrigger::ScrollRight,
+                _ => return Propagation::Proceed,
+            };
+            let action = app.mousebindings.borrow().action(trigger, controller.current_event_state());
+            match action {
+                Some(action) => {
+                    app.run(action);
+                    Propagation::Stop
+                },
+                None => Propagation::Proceed,
             }
-            Propagation::Proceed
         }),
     );
 
     draw.add_controller(scroll_controller);
 
-    draw.set_draw_func(glib::clone!(@weak elements => move |_, ctx, _, _| {
+    // the cache is rasterized at the scale of the output
+    draw.connect_scale_factor_notify(glib::clone!(@strong app => move |draw| {
+        app.backing.borrow_mut().invalidate();
+        draw.queue_draw();
+    }));
 
-        for element in elements.borrow_mut().iter() {
-            element.draw(ctx);
-        }
+    draw.set_draw_func(glib::clone!(@weak app => move |draw, ctx, width, height| {
+        let elements = app.elements.borrow();
+        // the active element is always the last one and is drawn by the live layer
+        let finished = match elements.last() {
+            Some(elem) if elem.active() => elements.len() - 1,
+            _ => elements.len(),
+        };
+
+        if let Err(error) = app.backing.borrow_mut().paint(ctx, width, height, output_scale(draw), &elements[..finished]) {
+            eprintln!("error drawing: {error}");
+        };
+    }));
 
-        if let Err(error) = ctx.fill() {
-            panic!("error drawing: {:?}", error)
+    live.set_draw_func(glib::clone!(@weak app => move |live, ctx, _, _| {
+        // elements use window coordinates, the live layer sits at its margins
+        ctx.translate(-live.margin_start() as f64, -live.margin_top() as f64);
+        if let Some(elem) = app.elements.borrow().last() {
+            if elem.active() {
+                if let Err(error) = elem.draw(ctx) {
+                    eprintln!("error drawing: {error}");
+                }
+            }
         };
     }));
 
     // load css for the transparency of the window
     let provider = gtk::CssProvider::new();
-    provider.load_from_data(include_str!("styles/style.css"));
+    provider.load_from_string(include_str!("styles/style.css"));
     gtk::style_context_add_provider_for_display(
         &Display::default().expect("error getting default display"),
         &provider,
         gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
     );
 
-    window.set_child(Some(&draw));
+    let overlay = gtk::Overlay::new();
+    overlay.set_child(Some(&draw));
+    overlay.add_overlay(&live);
+    overlay.add_overlay(app.swatch.widget());
+    overlay.add_overlay(app.toast.widget());
+
+    window.set_child(Some(&overlay));
     window.set_visible(true);
+
+    // the pen of the last session
+    let saved_preset = app
+        .state
+        .borrow()
+        .preset
+        .as_deref()
+        .and_then(|key| app.presets.borrow().position(key));
+    if let Some(index) = saved_preset {
+        app.run(Action::Preset(index));
+    }
+
+    if let Some(name) = app.profile.borrow().as_deref() {
+        app.toast.show(&format!("profile {name}"));
+    };
 }
 
-fn main() {
+// `chicolli --check-config [path]`, print what is wrong with the config and fail if anything is
+fn check_config(path: Option<std::path::PathBuf>) -> glib::ExitCode {
+    let Some(path) = path.or_else(|| config::get_config_file_loc(None)) else {
+        eprintln!("could not find the config file");
+        return glib::ExitCode::FAILURE;
+    };
+    let diagnostics = config::validate::check_file(&path);
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+    if diagnostics.is_empty() {
+        println!("{}: ok", path.display());
+        glib::ExitCode::SUCCESS
+    } else {
+        glib::ExitCode::FAILURE
+    }
+}
+
+fn main() -> glib::ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let options = match cli::Options::parse(args.iter().skip(1).cloned()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{}", cli::USAGE);
+            return glib::ExitCode::FAILURE;
+        }
+    };
+    if let Some(path) = options.check_config.clone() {
+        return check_config(path.or(options.config));
+    }
+
     let application = gtk::Application::new(Some("sh.wmww.gtk-layer-example"), Default::default());
 
-    application.connect_activate(|app| {
-        activate(app);
+    application.connect_activate(move |app| {
+        activate(app, &options);
     });
 
-    application.run();
+    // the options are handled above, gtk would reject them
+    application.run_with_args(&args[..1])
 }
@@ -0,0 +1,57 @@
+use crate::colors::{self, Color};
+use crate::config::Preset;
+use crate::drawing::drawing_tool::CurrentDrawingTool;
+use crate::drawing::line_style::LineStyle;
+use crate::keybindings::Action;
+
+/// A preset of the config, parsed. What is `None` stays as it is when the pen is selected.
+#[derive(Debug, Clone, Copy)]
+pub struct Pen {
+    pub tool: Option<CurrentDrawingTool>,
+    pub color: Option<Color>,
+    pub width: Option<f64>,
+    pub line_style: Option<LineStyle>,
+}
+
+/// The presets of the config, by their position in the list, with the keys that select them.
+pub struct Presets {
+    pens: Vec<Pen>,
+    keys: Vec<Option<String>>,
+}
+
+impl Presets {
+    /// fields that can not be parsed are left out of the pen, `config::validate` reports them
+    pub fn new(config: &[Preset]) -> Presets {
+        Presets {
+            pens: config
+                .iter()
+                .map(|preset| Pen {
+                    tool: preset.tool.as_deref().and_then(Action::tool_by_name),
+                    color: preset
+                        .color
+                        .as_deref()
+                        .and_then(|color| colors::parse(color).ok()),
+                    width: preset.width,
+                    line_style: preset.line_style,
+                })
+                .collect(),
+            keys: config.iter().map(|preset| preset.key.clone()).collect(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Pen> {
+        self.pens.get(index).copied()
+    }
+
+    /// the key of the preset at `index` as the config writes it
+    pub fn key(&self, index: usize) -> Option<&str> {
+        self.keys.get(index)?.as_deref()
+    }
+
+    /// the position of the preset selected with `key`
+    pub fn position(&self, key: &str) -> Option<usize> {
+        self.keys
+            .iter()
+            .position(|preset_key| preset_key.as_deref() == Some(key))
+    }
+}
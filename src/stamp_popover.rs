@@ -0,0 +1,63 @@
+use std::rc::Rc;
+
+use gtk::{gdk, glib, prelude::*};
+
+use crate::drawing::drawing_tool::Point;
+use crate::drawing::stamp::StampImage;
+
+const PREVIEW_SIZE: i32 = 40;
+// the previews wrap into rows of this many
+const PER_ROW: u32 = 6;
+
+/// The stamp picker on the layer surface, a grid with a preview of every stamp image.
+pub struct StampPopover {
+    popover: gtk::Popover,
+    grid: gtk::FlowBox,
+}
+
+impl StampPopover {
+    pub fn new(parent: &impl IsA<gtk::Widget>) -> StampPopover {
+        let popover = gtk::Popover::new();
+        popover.set_parent(parent);
+        popover.set_has_arrow(false);
+        let grid = gtk::FlowBox::new();
+        grid.set_selection_mode(gtk::SelectionMode::None);
+        grid.set_max_children_per_line(PER_ROW);
+        popover.set_child(Some(&grid));
+        parent.connect_destroy(glib::clone!(@weak popover => move |_| popover.unparent()));
+        StampPopover { popover, grid }
+    }
+
+    /// show the popover at `position`, `on_pick` gets the index of the chosen image
+    pub fn popup(&self, position: Point, images: &[StampImage], on_pick: Rc<dyn Fn(usize)>) {
+        // rebuilt every time, the stamps directory can change
+        while let Some(child) = self.grid.first_child() {
+            self.grid.remove(&child);
+        }
+        for (index, image) in images.iter().enumerate() {
+            let texture = gdk::Texture::for_pixbuf(&image.pixbuf);
+            let preview = gtk::Image::from_paintable(Some(&texture));
+            preview.set_pixel_size(PREVIEW_SIZE);
+
+            let button = gtk::Button::new();
+            button.add_css_class("flat");
+            button.set_tooltip_text(Some(&image.name));
+            button.set_child(Some(&preview));
+            button.connect_clicked(
+                glib::clone!(@weak self.popover as popover, @strong on_pick => move |_| {
+                    popover.popdown();
+                    on_pick(index);
+                }),
+            );
+            self.grid.insert(&button, -1);
+        }
+
+        self.popover.set_pointing_to(Some(&gdk::Rectangle::new(
+            position.0 as i32,
+            position.1 as i32,
+            1,
+            1,
+        )));
+        self.popover.popup();
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use gtk::gdk_pixbuf::Pixbuf;
+use gtk::glib;
+
+use crate::config;
+use crate::drawing::stamp::{StampImage, StampSource};
+
+// SVG images are rasterized this big once and bigger images are scaled down to it, stamps are
+// drawn scaled down from that
+const LOAD_SIZE: i32 = 512;
+
+/// The images of the stamps directory in the order of their file names, and what the stamp
+/// tool places: one of them or a symbol from the symbol picker.
+#[derive(Default)]
+pub struct Stamps {
+    images: Vec<StampImage>,
+    selected: usize,
+    symbol: Option<String>,
+}
+
+impl Stamps {
+    /// load the PNG and SVG files of the stamps directory, files that can not be loaded are
+    /// reported and skipped
+    pub fn load() -> Stamps {
+        let Some(dir) = config::get_stamps_config_loc() else {
+            return Stamps::default();
+        };
+        Stamps {
+            images: read_images(&dir),
+            ..Stamps::default()
+        }
+    }
+
+    pub fn images(&self) -> &[StampImage] {
+        &self.images
+    }
+
+    pub fn selected(&self) -> Option<StampSource> {
+        match &self.symbol {
+            Some(symbol) => Some(StampSource::Symbol(symbol.clone())),
+            None => self
+                .images
+                .get(self.selected)
+                .cloned()
+                .map(StampSource::Image),
+        }
+    }
+
+    /// select the image at `index`, `None` if there is no such image
+    pub fn select(&mut self, index: usize) -> Option<&StampImage> {
+        if index < self.images.len() {
+            self.selected = index;
+            self.symbol = None;
+        }
+        self.images.get(index)
+    }
+
+    pub fn select_symbol(&mut self, symbol: &str) {
+        self.symbol = Some(String::from(symbol));
+    }
+
+    /// select the image `step` places after the selected one, wrapping around at the ends. From
+    /// a symbol it goes back to the selected image.
+    pub fn cycle(&mut self, step: isize) -> Option<&StampImage> {
+        if self.images.is_empty() {
+            return None;
+        }
+        if self.symbol.is_some() {
+            return self.select(self.selected);
+        }
+        let next = (self.selected as isize + step).rem_euclid(self.images.len() as isize);
+        self.select(next as usize)
+    }
+
+    /// keep the selected image when the directory is read again, as long as it is still there
+    pub fn reload(&mut self) {
+        let mut reloaded = Stamps::load();
+        reloaded.symbol = self.symbol.take();
+        if let Some(path) = self
+            .images
+            .get(self.selected)
+            .map(|image| image.path.clone())
+        {
+            reloaded.selected = reloaded
+                .images
+                .iter()
+                .position(|image| image.path == path)
+                .unwrap_or(0);
+        }
+        *self = reloaded;
+    }
+}
+
+fn read_images(dir: &Path) -> Vec<StampImage> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // no stamps directory is fine, there just are no stamps
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            eprintln!("could not read the stamps in {}: {e}", dir.display());
+            return Vec::new();
+        }
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    extension.eq_ignore_ascii_case("png") || extension.eq_ignore_ascii_case("svg")
+                })
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            match load_image(&path) {
+                Ok(pixbuf) => Some(StampImage { name, path, pixbuf }),
+                Err(e) => {
+                    eprintln!("could not load stamp {}: {e}", path.display());
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+// small raster images keep their size, scaling them up when loading would only blur them
+fn load_image(path: &Path) -> Result<Pixbuf, glib::Error> {
+    match Pixbuf::file_info(path) {
+        Some((format, width, height))
+            if !format.is_scalable() && width <= LOAD_SIZE && height <= LOAD_SIZE =>
+        {
+            Pixbuf::from_file(path)
+        }
+        _ => Pixbuf::from_file_at_scale(path, LOAD_SIZE, LOAD_SIZE, true),
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::colors::{self, Color};
+use crate::error::{Error, Result};
+
+const STATE_DIR: &str = "chicolli";
+const STATE_NAME: &str = "state.json";
+// how many colors from the chooser are remembered
+const RECENT_COLORS: usize = 8;
+
+/// What is kept from one session to the next, in `~/.local/state/chicolli/state.json`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct State {
+    /// the key of the preset that was selected last, it stays with its preset when the list
+    /// is reordered
+    #[serde(deserialize_with = "preset_key")]
+    pub preset: Option<String>,
+    /// colors picked with the color chooser, the latest first, as CSS colors
+    pub recent_colors: Vec<String>,
+}
+
+// state files of older versions have the position of the preset, that is dropped instead of
+// making the whole state unreadable
+fn preset_key<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error> {
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| value.as_str().map(String::from)))
+}
+
+fn get_state_file_loc() -> Option<PathBuf> {
+    let mut path = dirs::state_dir().or_else(dirs::data_local_dir)?;
+    path.push(STATE_DIR);
+    path.push(STATE_NAME);
+    Some(path)
+}
+
+impl State {
+    /// a missing or broken state file gives an empty state
+    pub fn load() -> State {
+        let Some(path) = get_state_file_loc() else {
+            return State::default();
+        };
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return State::default(),
+            Err(e) => {
+                eprintln!("could not read {}: {}", path.display(), e);
+                return State::default();
+            }
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("invalid state file {}: {}", path.display(), e);
+            State::default()
+        })
+    }
+
+    /// the recent colors that can be parsed, with their CSS name
+    pub fn recent_colors(&self) -> Vec<(String, Color)> {
+        self.recent_colors
+            .iter()
+            .filter_map(|name| Some((name.clone(), colors::parse(name).ok()?)))
+            .collect()
+    }
+
+    /// put `color` in front of the recent colors
+    pub fn add_recent_color(&mut self, color: Color) {
+        let name = color.to_str().to_string();
+        self.recent_colors.retain(|recent| *recent != name);
+        self.recent_colors.insert(0, name);
+        self.recent_colors.truncate(RECENT_COLORS);
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = get_state_file_loc().ok_or(Error::NoStateDir)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use gtk::{cairo, glib, prelude::*};
+
+use crate::colors::{self, Color};
+use crate::toast::HideTimeout;
+
+const SWATCH_TIMEOUT: Duration = Duration::from_millis(1200);
+const SWATCH_SIZE: i32 = 48;
+// the squares behind the color that make its opacity visible
+const CHECKER_SIZE: f64 = 8.0;
+
+/// A patch of the current color above the toasts, shown briefly when the color is nudged.
+pub struct Swatch {
+    area: gtk::DrawingArea,
+    color: Rc<Cell<Color>>,
+    timeout: HideTimeout,
+}
+
+impl Swatch {
+    pub fn new() -> Swatch {
+        let color = Rc::new(Cell::new(colors::RED));
+        let area = gtk::DrawingArea::new();
+        area.add_css_class("swatch-preview");
+        area.set_content_width(SWATCH_SIZE);
+        area.set_content_height(SWATCH_SIZE);
+        area.set_halign(gtk::Align::Center);
+        area.set_valign(gtk::Align::End);
+        area.set_margin_bottom(96);
+        // never take events away from the drawing area
+        area.set_can_target(false);
+        area.set_visible(false);
+        area.set_draw_func(glib::clone!(@strong color => move |_, ctx, width, height| {
+            if let Err(e) = draw(ctx, color.get(), width as f64, height as f64) {
+                eprintln!("could not draw swatch: {e}");
+            }
+        }));
+        Swatch {
+            area,
+            color,
+            timeout: HideTimeout::default(),
+        }
+    }
+
+    pub fn widget(&self) -> &gtk::DrawingArea {
+        &self.area
+    }
+
+    pub fn show(&self, color: Color) {
+        self.color.set(color);
+        self.area.queue_draw();
+        self.timeout.show(&self.area, SWATCH_TIMEOUT);
+    }
+}
+
+// the color over a checkerboard, so its opacity shows
+fn draw(ctx: &cairo::Context, color: Color, width: f64, height: f64) -> Result<(), cairo::Error> {
+    ctx.set_source_rgb(0.8, 0.8, 0.8);
+    ctx.paint()?;
+    ctx.set_source_rgb(0.5, 0.5, 0.5);
+    let columns = (width / CHECKER_SIZE).ceil() as i32;
+    let rows = (height / CHECKER_SIZE).ceil() as i32;
+    for row in 0..rows {
+        for column in (row % 2..columns).step_by(2) {
+            ctx.rectangle(
+                column as f64 * CHECKER_SIZE,
+                row as f64 * CHECKER_SIZE,
+                CHECKER_SIZE,
+                CHECKER_SIZE,
+            );
+        }
+    }
+    ctx.fill()?;
+    ctx.set_source_rgba(
+        color.red().into(),
+        color.green().into(),
+        color.blue().into(),
+        color.alpha().into(),
+    );
+    ctx.paint()
+}
+
+impl Default for Swatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
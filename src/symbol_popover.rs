@@ -0,0 +1,129 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{gdk, glib, prelude::*};
+
+use crate::drawing::drawing_tool::Point;
+use crate::symbols;
+
+// the results wrap into rows of this many
+const PER_ROW: u32 = 10;
+const RESULTS_HEIGHT: i32 = 220;
+
+type OnPick = Rc<RefCell<Option<Rc<dyn Fn(String)>>>>;
+
+/// The symbol picker on the layer surface: a search entry, the favorites of the config and the
+/// symbols the search finds. It is used with the keyboard alone, typing searches, Return takes
+/// the first result, Tab and the arrow keys move through the symbols and Escape closes it.
+pub struct SymbolPopover {
+    popover: gtk::Popover,
+    search: gtk::SearchEntry,
+    favorites: gtk::FlowBox,
+    results: gtk::FlowBox,
+    on_pick: OnPick,
+}
+
+impl SymbolPopover {
+    pub fn new(parent: &impl IsA<gtk::Widget>) -> SymbolPopover {
+        let popover = gtk::Popover::new();
+        popover.set_parent(parent);
+        popover.set_has_arrow(false);
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        popover.set_child(Some(&content));
+        parent.connect_destroy(glib::clone!(@weak popover => move |_| popover.unparent()));
+
+        let search = gtk::SearchEntry::new();
+        search.set_placeholder_text(Some("Search symbols"));
+        content.append(&search);
+        let favorites = symbol_grid();
+        content.append(&favorites);
+        content.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+        let results = symbol_grid();
+        let scrolled = gtk::ScrolledWindow::new();
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.set_min_content_height(RESULTS_HEIGHT);
+        scrolled.set_child(Some(&results));
+        content.append(&scrolled);
+
+        let on_pick: OnPick = Rc::new(RefCell::new(None));
+        search.connect_search_changed(
+            glib::clone!(@weak popover, @weak results, @strong on_pick => move |search| {
+                fill(&results, &symbols::search(&search.text()), &popover, &on_pick);
+            }),
+        );
+        search.connect_activate(
+            glib::clone!(@weak popover, @strong on_pick => move |search| {
+                if let Some(first) = symbols::search(&search.text()).first() {
+                    pick(&popover, &on_pick, first);
+                }
+            }),
+        );
+        search.connect_stop_search(glib::clone!(@weak popover => move |_| popover.popdown()));
+
+        SymbolPopover {
+            popover,
+            search,
+            favorites,
+            results,
+            on_pick,
+        }
+    }
+
+    /// show the popover at `position` with an empty search, `on_pick` gets the chosen symbol
+    pub fn popup(&self, position: Point, favorites: &[String], on_pick: Rc<dyn Fn(String)>) {
+        *self.on_pick.borrow_mut() = Some(on_pick);
+        let favorites: Vec<&str> = favorites.iter().map(String::as_str).collect();
+        fill(&self.favorites, &favorites, &self.popover, &self.on_pick);
+        self.favorites.set_visible(!favorites.is_empty());
+        self.search.set_text("");
+        fill(
+            &self.results,
+            &symbols::search(""),
+            &self.popover,
+            &self.on_pick,
+        );
+
+        self.popover.set_pointing_to(Some(&gdk::Rectangle::new(
+            position.0 as i32,
+            position.1 as i32,
+            1,
+            1,
+        )));
+        self.popover.popup();
+        self.search.grab_focus();
+    }
+}
+
+fn symbol_grid() -> gtk::FlowBox {
+    let grid = gtk::FlowBox::new();
+    grid.set_selection_mode(gtk::SelectionMode::None);
+    grid.set_max_children_per_line(PER_ROW);
+    grid.set_min_children_per_line(PER_ROW);
+    grid.set_homogeneous(true);
+    grid
+}
+
+// replace the symbols of `grid`
+fn fill(grid: &gtk::FlowBox, symbols: &[&str], popover: &gtk::Popover, on_pick: &OnPick) {
+    while let Some(child) = grid.first_child() {
+        grid.remove(&child);
+    }
+    for symbol in symbols {
+        let button = gtk::Button::with_label(symbol);
+        button.add_css_class("flat");
+        button.add_css_class("symbol");
+        let symbol = String::from(*symbol);
+        button.connect_clicked(
+            glib::clone!(@weak popover, @strong on_pick => move |_| pick(&popover, &on_pick, &symbol)),
+        );
+        grid.insert(&button, -1);
+    }
+}
+
+fn pick(popover: &gtk::Popover, on_pick: &OnPick, symbol: &str) {
+    popover.popdown();
+    // cloned so the callback can open the popover again
+    let on_pick = on_pick.borrow().clone();
+    if let Some(on_pick) = on_pick {
+        on_pick(String::from(symbol));
+    }
+}
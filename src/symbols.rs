@@ -0,0 +1,131 @@
+/// The symbols of the symbol picker with the words it finds them by, marks and arrows first
+/// since those are what screenshots get annotated with the most.
+pub const SYMBOLS: &[(&str, &str)] = &[
+    ("✔", "check mark tick yes done ok"),
+    ("✘", "cross mark x no wrong fail"),
+    ("⚠", "warning caution sign"),
+    ("★", "star favorite black"),
+    ("➜", "arrow right heavy"),
+    ("✅", "check mark button green done"),
+    ("❌", "cross mark red wrong"),
+    ("❗", "exclamation mark red important"),
+    ("❓", "question mark red"),
+    ("⛔", "no entry stop forbidden"),
+    ("🚫", "prohibited forbidden not allowed"),
+    ("💡", "light bulb idea tip hint"),
+    ("📌", "pushpin pin note"),
+    ("📍", "round pushpin location"),
+    ("🔍", "magnifying glass search zoom"),
+    ("👉", "pointing right hand finger"),
+    ("👈", "pointing left hand finger"),
+    ("👆", "pointing up hand finger"),
+    ("👇", "pointing down hand finger"),
+    ("👍", "thumbs up like good yes"),
+    ("👎", "thumbs down dislike bad no"),
+    ("👀", "eyes look see attention"),
+    ("🔥", "fire hot"),
+    ("🐛", "bug insect error"),
+    ("🎉", "party popper celebrate tada"),
+    ("🔒", "locked lock secure"),
+    ("🔓", "unlocked lock open"),
+    ("⏱", "stopwatch timer time"),
+    ("⭐", "star yellow favorite"),
+    ("❤", "heart red love"),
+    ("😀", "grinning face smile happy"),
+    ("🙂", "slightly smiling face"),
+    ("😕", "confused face"),
+    ("😱", "screaming face fear shock"),
+    ("🤔", "thinking face"),
+    ("☺", "smiling face"),
+    ("☹", "frowning face sad"),
+    ("☑", "ballot box with check checkbox"),
+    ("☐", "ballot box empty checkbox"),
+    ("☒", "ballot box with x checkbox"),
+    ("✓", "check mark light tick"),
+    ("✗", "ballot x light cross"),
+    ("→", "arrow right"),
+    ("←", "arrow left"),
+    ("↑", "arrow up"),
+    ("↓", "arrow down"),
+    ("↔", "arrow left right"),
+    ("↕", "arrow up down"),
+    ("↗", "arrow up right north east"),
+    ("↘", "arrow down right south east"),
+    ("↙", "arrow down left south west"),
+    ("↖", "arrow up left north west"),
+    ("⇒", "double arrow right implies"),
+    ("⇐", "double arrow left"),
+    ("⇔", "double arrow left right equivalent"),
+    ("↩", "arrow return back undo"),
+    ("↪", "arrow forward redo"),
+    ("⟳", "clockwise arrow reload refresh"),
+    ("⬆", "arrow up heavy"),
+    ("⬇", "arrow down heavy"),
+    ("⬅", "arrow left heavy"),
+    ("➡", "arrow right heavy"),
+    ("☆", "star white outline"),
+    ("♥", "heart suit"),
+    ("●", "circle black dot bullet"),
+    ("○", "circle white"),
+    ("■", "square black"),
+    ("□", "square white"),
+    ("▲", "triangle up black"),
+    ("▼", "triangle down black"),
+    ("◆", "diamond black"),
+    ("✚", "plus heavy cross add"),
+    ("−", "minus remove"),
+    ("×", "multiplication times"),
+    ("÷", "division"),
+    ("±", "plus minus"),
+    ("≈", "almost equal approximately"),
+    ("≠", "not equal"),
+    ("≤", "less than or equal"),
+    ("≥", "greater than or equal"),
+    ("∞", "infinity"),
+    ("°", "degree"),
+    ("§", "section"),
+    ("¶", "pilcrow paragraph"),
+    ("†", "dagger"),
+    ("‼", "double exclamation mark"),
+    ("⁉", "exclamation question mark"),
+    ("ℹ", "information info"),
+    ("⌘", "command key mac"),
+    ("⌥", "option key alt mac"),
+    ("⇧", "shift key"),
+    ("⌫", "backspace erase key"),
+    ("⏎", "return enter key"),
+    ("⎋", "escape key"),
+    ("⚙", "gear settings cog"),
+    ("✎", "pencil edit"),
+    ("✂", "scissors cut"),
+    ("☎", "telephone phone"),
+    ("✉", "envelope mail email"),
+    ("⌛", "hourglass wait loading"),
+    ("⚡", "high voltage lightning fast"),
+    ("☀", "sun weather"),
+    ("☁", "cloud weather"),
+    ("☂", "umbrella rain"),
+    ("♻", "recycling symbol"),
+    ("✱", "asterisk heavy"),
+    ("❶", "dingbat number one circled"),
+    ("❷", "dingbat number two circled"),
+    ("❸", "dingbat number three circled"),
+    ("❹", "dingbat number four circled"),
+    ("❺", "dingbat number five circled"),
+];
+
+/// the symbols with a word starting with each word of `query`, all of them for an empty query.
+/// A symbol pasted into the query finds itself.
+pub fn search(query: &str) -> Vec<&'static str> {
+    let query = query.trim().to_lowercase();
+    SYMBOLS
+        .iter()
+        .filter(|(symbol, words)| {
+            *symbol == query
+                || query
+                    .split_whitespace()
+                    .all(|part| words.split(' ').any(|word| word.starts_with(part)))
+        })
+        .map(|(symbol, _)| *symbol)
+        .collect()
+}
@@ -0,0 +1,80 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use gtk::{glib, prelude::*};
+
+const TOAST_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Hides a widget a while after it was shown, showing it again before that restarts the wait.
+#[derive(Clone, Default)]
+pub struct HideTimeout(Rc<RefCell<Option<glib::SourceId>>>);
+
+impl HideTimeout {
+    /// show `widget` and hide it after `duration`
+    pub fn show(&self, widget: &impl IsA<gtk::Widget>, duration: Duration) {
+        if let Some(timeout) = self.0.borrow_mut().take() {
+            timeout.remove();
+        }
+        let widget = widget.upcast_ref::<gtk::Widget>();
+        widget.set_visible(true);
+
+        let pending = &self.0;
+        let timeout = glib::timeout_add_local_once(
+            duration,
+            glib::clone!(@weak widget, @strong pending => move || {
+                pending.borrow_mut().take();
+                widget.set_visible(false);
+            }),
+        );
+        *pending.borrow_mut() = Some(timeout);
+    }
+}
+
+/// A short message at the bottom of the overlay, hidden again after a few seconds.
+#[derive(Clone)]
+pub struct Toast {
+    label: gtk::Label,
+    timeout: HideTimeout,
+}
+
+impl Toast {
+    pub fn new() -> Toast {
+        let label = gtk::Label::new(None);
+        label.add_css_class("toast");
+        label.set_halign(gtk::Align::Center);
+        label.set_valign(gtk::Align::End);
+        label.set_margin_bottom(48);
+        label.set_wrap(true);
+        // never take events away from the drawing area
+        label.set_can_target(false);
+        label.set_visible(false);
+        Toast {
+            label,
+            timeout: HideTimeout::default(),
+        }
+    }
+
+    pub fn widget(&self) -> &gtk::Label {
+        &self.label
+    }
+
+    pub fn show(&self, message: &str) {
+        self.label.remove_css_class("error");
+        self.popup(message);
+    }
+
+    pub fn error(&self, message: &str) {
+        self.label.add_css_class("error");
+        self.popup(message);
+    }
+
+    fn popup(&self, message: &str) {
+        self.label.set_text(message);
+        self.timeout.show(&self.label, TOAST_TIMEOUT);
+    }
+}
+
+impl Default for Toast {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,65 @@
+use std::{cell::RefCell, path::Path, rc::Rc, time::Duration};
+
+use gio::{prelude::*, Cancellable, FileMonitor, FileMonitorEvent, FileMonitorFlags};
+use gtk::glib;
+
+// one save in an editor usually produces a burst of events, they are coalesced into one call
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watch `files` and `directories` and call `on_change` once things settled after a change.
+/// The returned monitors have to be kept alive for as long as the watch should last.
+pub fn watch(
+    files: &[&Path],
+    directories: &[&Path],
+    on_change: impl Fn() + 'static,
+) -> Vec<FileMonitor> {
+    let on_change = Rc::new(on_change);
+    let pending: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+
+    let monitors = files
+        .iter()
+        .map(|path| {
+            gio::File::for_path(path)
+                .monitor_file(FileMonitorFlags::WATCH_MOVES, None::<&Cancellable>)
+        })
+        .chain(directories.iter().map(|path| {
+            gio::File::for_path(path)
+                .monitor_directory(FileMonitorFlags::WATCH_MOVES, None::<&Cancellable>)
+        }));
+
+    let mut started = Vec::new();
+    for monitor in monitors {
+        let monitor = match monitor {
+            Ok(monitor) => monitor,
+            Err(e) => {
+                eprintln!("could not watch the config: {e}");
+                continue;
+            }
+        };
+        monitor.connect_changed(
+            glib::clone!(@strong on_change, @strong pending => move |_, _, _, event| {
+                if matches!(
+                    event,
+                    FileMonitorEvent::AttributeChanged
+                        | FileMonitorEvent::PreUnmount
+                        | FileMonitorEvent::Unmounted
+                ) {
+                    return;
+                }
+                if let Some(source) = pending.borrow_mut().take() {
+                    source.remove();
+                }
+                let source = glib::timeout_add_local_once(
+                    DEBOUNCE,
+                    glib::clone!(@strong on_change, @strong pending => move || {
+                        pending.borrow_mut().take();
+                        on_change();
+                    }),
+                );
+                *pending.borrow_mut() = Some(source);
+            }),
+        );
+        started.push(monitor);
+    }
+    started
+}